@@ -0,0 +1,460 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use tar::EntryType;
+use zip::ZipArchive;
+
+use crate::helpers::name_helper::validate_name;
+
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+}
+
+// A file or directory read from an archive, the path is split into its (safe) segments
+pub struct ArchiveFile {
+    pub path: Vec<String>,
+    pub is_directory: bool,
+    pub bytes: Vec<u8>,
+}
+
+// Result of reading a part of an archive
+pub struct ArchivePart {
+    pub files: Vec<ArchiveFile>,
+    // Index of the entry to continue with in the next part
+    pub next_index: usize,
+    // Byte offset of the entry to continue with, tar archives are read from this offset
+    pub next_offset: u64,
+    pub is_complete: bool,
+}
+
+// Reads the content of a file from its chunks without copying them into a single buffer
+pub struct ChunkReader<'a> {
+    chunks: Vec<&'a [u8]>,
+    // Offset of every chunk in the content
+    offsets: Vec<u64>,
+    size: u64,
+    position: u64,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub fn new(chunks: Vec<&'a [u8]>) -> Self {
+        let mut offsets: Vec<u64> = vec![];
+        let mut size: u64 = 0;
+        for chunk in &chunks {
+            offsets.push(size);
+            size += chunk.len() as u64;
+        }
+
+        Self {
+            chunks,
+            offsets,
+            size,
+            position: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // The last chunk that starts at or before the position
+        let index = self
+            .offsets
+            .partition_point(|offset| *offset <= self.position);
+        let chunk = match index
+            .checked_sub(1)
+            .map(|_index| (_index, self.chunks[_index]))
+        {
+            Some((_index, _chunk)) if self.position < self.size => {
+                &_chunk[(self.position - self.offsets[_index]) as usize..]
+            }
+            _ => return Ok(0),
+        };
+
+        let length = chunk.len().min(buf.len());
+        buf[..length].copy_from_slice(&chunk[..length]);
+        self.position += length as u64;
+        Ok(length)
+    }
+}
+
+impl Seek for ChunkReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        match position {
+            Some(_position) => {
+                self.position = _position;
+                Ok(_position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid seek to a negative position",
+            )),
+        }
+    }
+}
+
+// Reader that starts at an offset of the inner reader, positions are relative to the offset
+struct OffsetReader<R> {
+    inner: R,
+    start: u64,
+}
+
+impl<R: Read> Read for OffsetReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for OffsetReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => SeekFrom::Start(self.start + offset),
+            _ => pos,
+        };
+        let position = self.inner.seek(pos)?;
+        position.checked_sub(self.start).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek before the start")
+        })
+    }
+}
+
+// Detect the archive format by its magic bytes
+pub fn get_archive_format<R: Read + Seek>(reader: &mut R) -> Option<ArchiveFormat> {
+    let mut bytes: Vec<u8> = vec![];
+    reader.by_ref().take(262).read_to_end(&mut bytes).ok()?;
+    reader.rewind().ok()?;
+
+    if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        return Some(ArchiveFormat::Zip);
+    }
+
+    // `ustar` magic of the POSIX and GNU header at offset 257
+    match bytes.get(257..262) {
+        Some(b"ustar") => Some(ArchiveFormat::Tar),
+        _ => None,
+    }
+}
+
+// Split the path of an entry into (normalized) segments, entries with invalid names, entries that
+// would end up outside of the target directory (`..`) and macOS resource forks are ignored
+// ex: `./site//css/main.css` -> ['site', 'css', 'main.css']
+pub fn get_entry_path(name: &str) -> Option<Vec<String>> {
+    let mut path: Vec<String> = vec![];
+    for segment in name.replace('\\', "/").split('/') {
+        match segment {
+            "" | "." => {}
+            _ => path.push(validate_name(segment, false).ok()?),
+        }
+    }
+
+    match path.first().map(|segment| segment.as_str()) {
+        None | Some("__MACOSX") => None,
+        Some(_) => Some(path),
+    }
+}
+
+// Read the entries of the archive starting at `start_index` (at byte `start_offset` for tar),
+// stops before the combined size of the read files exceeds `max_size` or `max_entries` entries
+// are read (at least one entry is read), the content is only read if requested
+pub fn read_archive<R: Read + Seek>(
+    reader: R,
+    format: &ArchiveFormat,
+    start_index: usize,
+    start_offset: u64,
+    max_size: u64,
+    max_entries: usize,
+    read_content: bool,
+) -> Result<ArchivePart, String> {
+    match format {
+        ArchiveFormat::Zip => read_zip(reader, start_index, max_size, max_entries, read_content),
+        ArchiveFormat::Tar => read_tar(
+            reader,
+            start_index,
+            start_offset,
+            max_size,
+            max_entries,
+            read_content,
+        ),
+    }
+}
+
+fn read_zip<R: Read + Seek>(
+    reader: R,
+    start_index: usize,
+    max_size: u64,
+    max_entries: usize,
+    read_content: bool,
+) -> Result<ArchivePart, String> {
+    let mut archive =
+        ZipArchive::new(reader).map_err(|err| format!("Zip archive could not be read: {}", err))?;
+
+    let mut files: Vec<ArchiveFile> = vec![];
+    let mut size: u64 = 0;
+
+    for index in start_index..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|err| format!("Zip archive could not be read: {}", err))?;
+
+        if index - start_index >= max_entries || (size > 0 && size + entry.size() > max_size) {
+            return Ok(ArchivePart {
+                files,
+                next_index: index,
+                next_offset: 0,
+                is_complete: false,
+            });
+        }
+        size += entry.size();
+
+        if let Some(path) = get_entry_path(entry.name()) {
+            let mut content: Vec<u8> = vec![];
+            if read_content && !entry.is_dir() {
+                entry.read_to_end(&mut content).map_err(|err| {
+                    format!("File {} could not be extracted: {}", entry.name(), err)
+                })?;
+            }
+
+            files.push(ArchiveFile {
+                path,
+                is_directory: entry.is_dir(),
+                bytes: content,
+            });
+        }
+    }
+
+    Ok(ArchivePart {
+        files,
+        next_index: archive.len(),
+        next_offset: 0,
+        is_complete: true,
+    })
+}
+
+fn read_tar<R: Read + Seek>(
+    mut reader: R,
+    start_index: usize,
+    start_offset: u64,
+    max_size: u64,
+    max_entries: usize,
+    read_content: bool,
+) -> Result<ArchivePart, String> {
+    // Continue at the header of the next entry instead of reading all previous headers again
+    reader
+        .seek(SeekFrom::Start(start_offset))
+        .map_err(|err| format!("Tar archive could not be read: {}", err))?;
+
+    let mut archive = tar::Archive::new(OffsetReader {
+        inner: reader,
+        start: start_offset,
+    });
+    let entries = archive
+        .entries_with_seek()
+        .map_err(|err| format!("Tar archive could not be read: {}", err))?;
+
+    let mut files: Vec<ArchiveFile> = vec![];
+    let mut size: u64 = 0;
+    let mut next_index = start_index;
+    let mut next_offset = start_offset;
+
+    for (count, entry) in entries.enumerate() {
+        let mut entry = entry.map_err(|err| format!("Tar archive could not be read: {}", err))?;
+
+        if count >= max_entries || (size > 0 && size + entry.size() > max_size) {
+            return Ok(ArchivePart {
+                files,
+                next_index,
+                next_offset,
+                is_complete: false,
+            });
+        }
+        size += entry.size();
+        next_index = start_index + count + 1;
+        // The positions are relative to the start offset, the data is padded to 512 byte blocks
+        next_offset = start_offset + entry.raw_file_position() + entry.size().div_ceil(512) * 512;
+
+        // Links and special files are not supported
+        let is_directory = match entry.header().entry_type() {
+            EntryType::Directory => true,
+            EntryType::Regular | EntryType::Continuous => false,
+            _ => continue,
+        };
+
+        let name = match entry.path() {
+            Ok(_path) => _path.to_string_lossy().to_string(),
+            Err(_) => continue,
+        };
+
+        if let Some(path) = get_entry_path(&name) {
+            let mut content: Vec<u8> = vec![];
+            if read_content && !is_directory {
+                entry
+                    .read_to_end(&mut content)
+                    .map_err(|err| format!("File {} could not be extracted: {}", name, err))?;
+            }
+
+            files.push(ArchiveFile {
+                path,
+                is_directory,
+                bytes: content,
+            });
+        }
+    }
+
+    Ok(ArchivePart {
+        files,
+        next_index,
+        next_offset,
+        is_complete: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+
+    fn create_tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for (path, bytes) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *bytes).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn create_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(vec![]));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (path, bytes) in files {
+            writer.start_file(*path, options).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn get_paths(files: &[ArchiveFile]) -> Vec<String> {
+        files.iter().map(|file| file.path.join("/")).collect()
+    }
+
+    #[test]
+    fn chunk_reader_reads_across_chunks() {
+        let chunks: Vec<&[u8]> = vec![b"ab", b"", b"cde"];
+        let mut reader = ChunkReader::new(chunks);
+
+        let mut content: Vec<u8> = vec![];
+        reader.read_to_end(&mut content).unwrap();
+        assert_eq!(content, b"abcde");
+
+        let mut rest: Vec<u8> = vec![];
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"bcde");
+
+        reader.seek(SeekFrom::End(-1)).unwrap();
+        let mut last: Vec<u8> = vec![];
+        reader.read_to_end(&mut last).unwrap();
+        assert_eq!(last, b"e");
+
+        assert!(reader.seek(SeekFrom::Current(-10)).is_err());
+    }
+
+    #[test]
+    fn archive_format_from_chunks() {
+        let tar = create_tar(&[("a.txt", b"a")]);
+        let (head, tail) = tar.split_at(100);
+        assert!(matches!(
+            get_archive_format(&mut ChunkReader::new(vec![head, tail])),
+            Some(ArchiveFormat::Tar)
+        ));
+
+        let zip = create_zip(&[("a.txt", b"a")]);
+        assert!(matches!(
+            get_archive_format(&mut ChunkReader::new(vec![&zip])),
+            Some(ArchiveFormat::Zip)
+        ));
+    }
+
+    #[test]
+    fn tar_resumes_at_entry_offset() {
+        let long_name = format!("{}/file.txt", "directory".repeat(15));
+        let tar = create_tar(&[
+            ("a.txt", b"first"),
+            (&long_name, &[1; 700]),
+            ("b.txt", b""),
+            ("c.txt", b"last"),
+        ]);
+
+        let all = read_archive(
+            Cursor::new(&tar),
+            &ArchiveFormat::Tar,
+            0,
+            0,
+            u64::MAX,
+            usize::MAX,
+            true,
+        )
+        .unwrap();
+
+        let mut files: Vec<ArchiveFile> = vec![];
+        let (mut index, mut offset) = (0, 0);
+        loop {
+            let part = read_archive(
+                Cursor::new(&tar),
+                &ArchiveFormat::Tar,
+                index,
+                offset,
+                u64::MAX,
+                1,
+                true,
+            )
+            .unwrap();
+            assert!(part.files.len() <= 1);
+            files.extend(part.files);
+            (index, offset) = (part.next_index, part.next_offset);
+            if part.is_complete {
+                break;
+            }
+        }
+
+        assert_eq!(index, 4);
+        assert_eq!(get_paths(&files), get_paths(&all.files));
+        assert_eq!(files[1].path.last().unwrap(), "file.txt");
+        assert_eq!(files[1].bytes, vec![1; 700]);
+        assert_eq!(files[3].bytes, b"last");
+    }
+
+    #[test]
+    fn zip_limits_entries_and_size() {
+        let zip = create_zip(&[("a.txt", b"aaaa"), ("b.txt", b"bbbb"), ("c.txt", b"cccc")]);
+
+        let part = read_archive(
+            Cursor::new(&zip),
+            &ArchiveFormat::Zip,
+            0,
+            0,
+            u64::MAX,
+            2,
+            true,
+        )
+        .unwrap();
+        assert_eq!(get_paths(&part.files), vec!["a.txt", "b.txt"]);
+        assert_eq!(part.next_index, 2);
+        assert!(!part.is_complete);
+
+        // At least one entry is read, even if it is larger than the maximum size
+        let part = read_archive(Cursor::new(&zip), &ArchiveFormat::Zip, 1, 0, 1, 10, true).unwrap();
+        assert_eq!(get_paths(&part.files), vec!["b.txt"]);
+        assert_eq!(part.files[0].bytes, b"bbbb");
+        assert_eq!(part.next_index, 2);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::models::{
+    error_models::FileManagerError,
+    http_models::{HeaderField, HeaderPreset, HeaderRule},
+};
+
+// Headers that are set by the canister itself and can not be overwritten
+// The content type and `nosniff` keep browsers from running uploaded content as another type
+const RESERVED_HEADERS: [&str; 6] = [
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "accept-ranges",
+    "content-type",
+    "x-content-type-options",
+];
+
+pub const MAX_HEADERS: usize = 32;
+pub const MAX_HEADER_VALUE_LENGTH: usize = 4096;
+
+pub fn get_preset_headers(preset: &HeaderPreset) -> Vec<HeaderField> {
+    match preset {
+        HeaderPreset::SecureWebsite => vec![
+            HeaderField(
+                "content-security-policy".to_string(),
+                "default-src 'self'; img-src 'self' data: blob:; style-src 'self' 'unsafe-inline'; object-src 'none'; base-uri 'self'; frame-ancestors 'none'"
+                    .to_string(),
+            ),
+            HeaderField(
+                "strict-transport-security".to_string(),
+                "max-age=31536000; includeSubDomains".to_string(),
+            ),
+            HeaderField("x-frame-options".to_string(), "DENY".to_string()),
+            HeaderField(
+                "referrer-policy".to_string(),
+                "strict-origin-when-cross-origin".to_string(),
+            ),
+            HeaderField(
+                "permissions-policy".to_string(),
+                "camera=(), microphone=(), geolocation=()".to_string(),
+            ),
+        ],
+    }
+}
+
+// Lowercase the header names and check if the headers can be set on an asset
+pub fn validate_headers(headers: Vec<HeaderField>) -> Result<Vec<HeaderField>, FileManagerError> {
+    if headers.len() > MAX_HEADERS {
+        return Err(FileManagerError::QuotaExceeded {
+            limit: MAX_HEADERS as u64,
+        });
+    }
+
+    headers
+        .into_iter()
+        .map(|HeaderField(name, value)| {
+            let name = name.trim().to_lowercase();
+
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+            {
+                return Err(FileManagerError::InvalidHeader(name));
+            }
+
+            if RESERVED_HEADERS.contains(&name.as_str()) {
+                return Err(FileManagerError::ProtectedHeader(name));
+            }
+
+            if value.len() > MAX_HEADER_VALUE_LENGTH || value.contains(['\r', '\n']) {
+                return Err(FileManagerError::InvalidHeader(name));
+            }
+
+            Ok(HeaderField(name, value.trim().to_string()))
+        })
+        .collect()
+}
+
+// Add the headers to the base headers, headers with the same name are overwritten
+pub fn merge_headers(base: &mut Vec<HeaderField>, headers: &[HeaderField]) {
+    for header in headers {
+        match base
+            .iter_mut()
+            .find(|existing| existing.0.eq_ignore_ascii_case(&header.0))
+        {
+            Some(existing) => existing.1 = header.1.clone(),
+            None => base.push(header.clone()),
+        }
+    }
+}
+
+// Parse a Netlify style `_headers` file; invalid header lines are skipped
+//
+// /*
+//   X-Frame-Options: DENY
+// /images/*
+//   Cache-Control: public, max-age=604800
+pub fn parse_headers_file(content: &str) -> Vec<HeaderRule> {
+    let mut rules: Vec<HeaderRule> = vec![];
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Non indented lines start a new path pattern
+        if !line.starts_with([' ', '\t']) {
+            rules.push(HeaderRule {
+                pattern: trimmed.to_string(),
+                headers: vec![],
+            });
+            continue;
+        }
+
+        if let (Some(rule), Some((name, value))) = (rules.last_mut(), trimmed.split_once(':')) {
+            if let Ok(headers) =
+                validate_headers(vec![HeaderField(name.to_string(), value.to_string())])
+            {
+                merge_headers(&mut rule.headers, &headers);
+            }
+        }
+    }
+
+    rules.retain(|rule| !rule.headers.is_empty());
+    rules
+}
+
+// Match a path (ex; `/images/logo.png`) against a `_headers` pattern
+// `*` matches the rest of the path, `:placeholder` matches a single segment
+pub fn match_header_pattern(pattern: &str, path: &str) -> bool {
+    let pattern_segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    for (index, pattern_segment) in pattern_segments.iter().enumerate() {
+        if *pattern_segment == "*" {
+            return true;
+        }
+
+        match path_segments.get(index) {
+            None => return false,
+            Some(path_segment) => {
+                if pattern_segment.starts_with(':') {
+                    continue;
+                }
+
+                // A trailing wildcard in the last segment (ex; `/assets/main.*`)
+                if let Some(prefix) = pattern_segment.strip_suffix('*') {
+                    return index == pattern_segments.len() - 1 && path_segment.starts_with(prefix);
+                }
+
+                if pattern_segment != path_segment {
+                    return false;
+                }
+            }
+        }
+    }
+
+    pattern_segments.len() == path_segments.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_header_pattern_wildcards() {
+        assert!(match_header_pattern("/*", "/index.html"));
+        assert!(match_header_pattern("/images/*", "/images/a/logo.png"));
+        assert!(match_header_pattern(
+            "/assets/main.*",
+            "/assets/main.3f2a.js"
+        ));
+        assert!(!match_header_pattern("/assets/main.*", "/assets/other.js"));
+        assert!(!match_header_pattern("/images/*", "/fonts/a.woff"));
+    }
+
+    #[test]
+    fn match_header_pattern_placeholders_and_exact() {
+        assert!(match_header_pattern("/blog/:slug", "/blog/hello"));
+        assert!(!match_header_pattern("/blog/:slug", "/blog/hello/world"));
+        assert!(match_header_pattern("/about", "/about"));
+        assert!(!match_header_pattern("/about", "/about/team"));
+        assert!(!match_header_pattern("/about/team", "/about"));
+    }
+
+    #[test]
+    fn parse_headers_file_rules() {
+        let content = "# comment\n/*\n  X-Frame-Options: DENY\n\n/images/*\n\tCache-Control: public, max-age=604800\n";
+        let rules = parse_headers_file(content);
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].pattern, "/*");
+        assert_eq!(rules[0].headers[0].0, "x-frame-options");
+        assert_eq!(rules[0].headers[0].1, "DENY");
+        assert_eq!(rules[1].pattern, "/images/*");
+        assert_eq!(rules[1].headers[0].1, "public, max-age=604800");
+    }
+
+    #[test]
+    fn parse_headers_file_skips_invalid_lines() {
+        let content = "/*\n  Content-Type: text/html\n  no separator\n  bad name: value\n/empty\n";
+        assert!(parse_headers_file(content).is_empty());
+    }
+
+    #[test]
+    fn parse_headers_file_merges_duplicates() {
+        let content = "/*\n  X-Test: a\n  x-test: b\n";
+        let rules = parse_headers_file(content);
+
+        assert_eq!(rules[0].headers.len(), 1);
+        assert_eq!(rules[0].headers[0].1, "b");
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::Cursor;
+
+use image::{io::Reader, DynamicImage, ImageFormat, ImageOutputFormat};
+
+// Widths of the resized variants that are generated for image files
+pub const IMAGE_VARIANT_WIDTHS: [u32; 4] = [128, 256, 512, 1024];
+
+// Larger images are not decoded, decoding and resizing would exceed the instruction limit
+pub const MAX_IMAGE_PIXELS: u64 = 4096 * 4096;
+
+const JPEG_QUALITY: u8 = 80;
+
+pub struct ResizedImage {
+    pub width: u32,
+    pub height: u32,
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
+
+pub fn is_resizable_image(mime_type: &str) -> bool {
+    get_image_format(mime_type).is_some()
+}
+
+fn get_image_format(mime_type: &str) -> Option<ImageFormat> {
+    match mime_type {
+        "image/png" => Some(ImageFormat::Png),
+        "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+        "image/webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+// Read the dimensions from the image header without decoding the image
+pub fn get_image_dimensions(bytes: &[u8], mime_type: &str) -> Result<(u32, u32), String> {
+    let format = get_image_format(mime_type).ok_or("File is not a supported image")?;
+    Reader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|err| format!("Image could not be read: {}", err))
+}
+
+// Create a resized variant for every preset width that is smaller than the original image
+// jpeg images are encoded as jpeg, png and webp images as (lossless) png
+pub fn resize_image(bytes: &[u8], mime_type: &str) -> Result<Vec<ResizedImage>, String> {
+    let format = get_image_format(mime_type).ok_or("File is not a supported image")?;
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(|err| format!("Image could not be decoded: {}", err))?;
+
+    let (output_format, output_mime_type) = match format {
+        ImageFormat::Jpeg => (ImageOutputFormat::Jpeg(JPEG_QUALITY), "image/jpeg"),
+        _ => (ImageOutputFormat::Png, "image/png"),
+    };
+
+    IMAGE_VARIANT_WIDTHS
+        .iter()
+        .filter(|width| **width < image.width())
+        .map(|width| {
+            let mut resized = image.thumbnail(*width, u32::MAX);
+            if format == ImageFormat::Jpeg {
+                // jpeg does not support an alpha channel
+                resized = DynamicImage::ImageRgb8(resized.to_rgb8());
+            }
+
+            let mut output: Vec<u8> = vec![];
+            resized
+                .write_to(&mut Cursor::new(&mut output), output_format.clone())
+                .map_err(|err| format!("Image could not be encoded: {}", err))?;
+
+            Ok(ResizedImage {
+                width: resized.width(),
+                height: resized.height(),
+                mime_type: output_mime_type.to_string(),
+                bytes: output,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+use crate::models::{
+    error_models::FileManagerError,
+    metadata_models::{AssetMetadata, MetadataChange, MetadataValue},
+};
+
+pub const MAX_METADATA_ENTRIES: usize = 32;
+pub const MAX_METADATA_KEY_LENGTH: usize = 64;
+pub const MAX_METADATA_TEXT_LENGTH: usize = 1024;
+pub const MAX_TAGS: usize = 32;
+pub const MAX_TAG_LENGTH: usize = 64;
+
+// Apply the changes to a copy of the metadata, removals are applied before additions
+pub fn apply_metadata_change(
+    metadata: &AssetMetadata,
+    change: MetadataChange,
+) -> Result<AssetMetadata, FileManagerError> {
+    let mut metadata = metadata.clone();
+
+    for key in change.remove {
+        metadata.entries.remove(key.trim());
+    }
+    for (key, value) in change.set {
+        metadata.entries.insert(key.trim().to_string(), value);
+    }
+
+    for tag in change.remove_tags {
+        metadata.tags.remove(&normalize_tag(&tag));
+    }
+    for tag in change.add_tags {
+        metadata.tags.insert(normalize_tag(&tag));
+    }
+
+    validate_metadata(metadata)
+}
+
+// Normalize the tags and check if the metadata fits within the limits
+pub fn validate_metadata(metadata: AssetMetadata) -> Result<AssetMetadata, FileManagerError> {
+    if metadata.entries.len() > MAX_METADATA_ENTRIES {
+        return Err(FileManagerError::QuotaExceeded {
+            limit: MAX_METADATA_ENTRIES as u64,
+        });
+    }
+
+    if metadata.tags.len() > MAX_TAGS {
+        return Err(FileManagerError::QuotaExceeded {
+            limit: MAX_TAGS as u64,
+        });
+    }
+
+    for (key, value) in &metadata.entries {
+        let is_valid_value = match value {
+            MetadataValue::Text(text) => text.len() <= MAX_METADATA_TEXT_LENGTH,
+            // NaN is never equal to itself and could not be found by a filter
+            MetadataValue::Number(number) => number.is_finite(),
+            MetadataValue::Bool(_) => true,
+        };
+
+        if !is_valid_key(key, MAX_METADATA_KEY_LENGTH) || !is_valid_value {
+            return Err(FileManagerError::InvalidMetadata(key.clone()));
+        }
+    }
+
+    let tags = metadata.tags.into_iter().map(|tag| normalize_tag(&tag));
+    let metadata = AssetMetadata {
+        entries: metadata.entries,
+        tags: tags.collect(),
+    };
+
+    match metadata
+        .tags
+        .iter()
+        .find(|tag| !is_valid_key(tag, MAX_TAG_LENGTH))
+    {
+        Some(_tag) => Err(FileManagerError::InvalidMetadata(_tag.clone())),
+        None => Ok(metadata),
+    }
+}
+
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+fn is_valid_key(key: &str, max_length: usize) -> bool {
+    !key.is_empty() && key.len() <= max_length && !key.chars().any(char::is_control)
+}
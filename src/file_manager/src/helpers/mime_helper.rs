@@ -0,0 +1,183 @@
+pub const DEFAULT_MIME_TYPE: &str = "application/octet-stream";
+
+// Types that are rendered or executed by the browser, only allowed when they match the extension
+const ACTIVE_MIME_TYPES: [&str; 7] = [
+    "text/html",
+    "application/xhtml+xml",
+    "image/svg+xml",
+    "text/javascript",
+    "application/javascript",
+    "text/xml",
+    "application/xml",
+];
+
+// Magic bytes at the start of the content for the types that can be verified
+const SIGNATURES: [(&[u8], &str); 10] = [
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"PK\x05\x06", "application/zip"),
+    (b"\0asm", "application/wasm"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+];
+
+// Get the mime type of a file by its extension (without the dot)
+pub fn get_mime_type(extension: &str) -> &'static str {
+    match extension.to_lowercase().as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" | "map" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "tar" => "application/x-tar",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => DEFAULT_MIME_TYPE,
+    }
+}
+
+// Get the extension of a file name, empty if the name has none
+// ex: `archive.tar` -> `tar`, `.well-known` -> ``
+pub fn get_extension(file_name: &str) -> &str {
+    match file_name.rsplit_once('.') {
+        Some((name, extension)) if !name.is_empty() => extension,
+        _ => "",
+    }
+}
+
+// Get the mime type for an uploaded file, the extension takes precedence over the declared type
+// Unknown extensions keep the declared type, unless it is invalid or could be rendered by the browser
+// ex: (`png`, `text/html`) -> `image/png`, (`bin`, `text/html`) -> `application/octet-stream`
+pub fn resolve_mime_type(extension: &str, declared_mime_type: &str) -> String {
+    let mime_type = get_mime_type(extension);
+    if mime_type != DEFAULT_MIME_TYPE {
+        return mime_type.to_string();
+    }
+
+    let declared_mime_type = declared_mime_type.trim().to_lowercase();
+    if is_valid_mime_type(&declared_mime_type)
+        && !ACTIVE_MIME_TYPES.contains(&declared_mime_type.as_str())
+    {
+        declared_mime_type
+    } else {
+        DEFAULT_MIME_TYPE.to_string()
+    }
+}
+
+// A mime type consists of a type and subtype, parameters are not allowed
+pub fn is_valid_mime_type(mime_type: &str) -> bool {
+    match mime_type.split_once('/') {
+        Some((_type, subtype)) => [_type, subtype].iter().all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+        }),
+        None => false,
+    }
+}
+
+// Detect the mime type by the magic bytes at the start of the content
+pub fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+        .map(|(_, mime_type)| *mime_type)
+}
+
+// Check if the content matches the mime type, types without a known signature always match
+pub fn matches_signature(mime_type: &str, bytes: &[u8]) -> bool {
+    let has_signature = mime_type == "image/webp"
+        || SIGNATURES
+            .iter()
+            .any(|(_, signature_mime_type)| *signature_mime_type == mime_type);
+
+    !has_signature || sniff_mime_type(bytes) == Some(mime_type)
+}
+
+// Text based types of which the content can be searched
+pub fn is_text_mime_type(mime_type: &str) -> bool {
+    mime_type.starts_with("text/")
+        || matches!(
+            mime_type,
+            "application/json"
+                | "application/manifest+json"
+                | "application/xml"
+                | "application/xhtml+xml"
+                | "application/javascript"
+                | "image/svg+xml"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_extension_of_names() {
+        assert_eq!(get_extension("archive.tar"), "tar");
+        assert_eq!(get_extension("archive.tar.gz"), "gz");
+        assert_eq!(get_extension(".well-known"), "");
+        assert_eq!(get_extension("README"), "");
+    }
+
+    #[test]
+    fn resolve_mime_type_prefers_extension() {
+        assert_eq!(resolve_mime_type("PNG", "text/html"), "image/png");
+        assert_eq!(resolve_mime_type("bin", "text/html"), DEFAULT_MIME_TYPE);
+        assert_eq!(resolve_mime_type("bin", "Audio/Ogg "), "audio/ogg");
+        assert_eq!(
+            resolve_mime_type("bin", "text/plain; charset=utf-8"),
+            DEFAULT_MIME_TYPE
+        );
+    }
+
+    #[test]
+    fn sniff_mime_type_signatures() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_mime_type(b"<html>"), None);
+    }
+
+    #[test]
+    fn matches_signature_only_for_known_types() {
+        assert!(matches_signature("image/png", b"\x89PNG\r\n\x1a\n"));
+        assert!(!matches_signature("image/png", b"<script>"));
+        assert!(matches_signature("text/plain", b"anything"));
+    }
+
+    #[test]
+    fn text_mime_types() {
+        assert!(is_text_mime_type("text/markdown"));
+        assert!(is_text_mime_type("application/json"));
+        assert!(!is_text_mime_type("image/png"));
+    }
+}
@@ -0,0 +1,64 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::models::error_models::{FileManagerError, InvalidNameReason};
+
+// Maximum length of a file or directory name in bytes
+pub const MAX_NAME_LENGTH: usize = 255;
+
+const INVALID_CHARS: [char; 9] = ['/', '*', '\\', ':', '?', '"', '<', '>', '\''];
+
+// Paths that are served by `http_request` itself and would hide assets with the same name
+const RESERVED_NAMES: [&str; 1] = ["directories"];
+
+// Validate the name of a file or directory and return it in Unicode NFC form
+// Reserved names are only rejected in a document root (the root or a directory mapped to a host)
+pub fn validate_name(name: &str, is_document_root: bool) -> Result<String, FileManagerError> {
+    let name: String = name.nfc().collect();
+    let invalid = |name: &str, reason: InvalidNameReason| FileManagerError::InvalidName {
+        name: name.to_string(),
+        reason,
+    };
+
+    if name.trim().is_empty() {
+        return Err(invalid(&name, InvalidNameReason::Empty));
+    }
+
+    if name == "." || name == ".." {
+        return Err(invalid(&name, InvalidNameReason::InvalidCharacters));
+    }
+
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(invalid(
+            &name,
+            InvalidNameReason::TooLong {
+                max_length: MAX_NAME_LENGTH as u64,
+            },
+        ));
+    }
+
+    if name
+        .chars()
+        .any(|c| c.is_control() || INVALID_CHARS.contains(&c))
+    {
+        return Err(invalid(&name, InvalidNameReason::InvalidCharacters));
+    }
+
+    if is_document_root && is_reserved_name(&name) {
+        return Err(invalid(&name, InvalidNameReason::Reserved));
+    }
+
+    Ok(name)
+}
+
+pub fn is_reserved_name(name: &str) -> bool {
+    RESERVED_NAMES.contains(&name)
+}
+
+// Split a path into its names in Unicode NFC form, empty and `.` segments are skipped
+// ex: `/docs//guide/./intro.md` -> ['docs', 'guide', 'intro.md']
+pub fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .map(|segment| segment.nfc().collect())
+        .collect()
+}
@@ -0,0 +1,153 @@
+// Match a name against a glob pattern, `*` matches any characters and `?` a single character
+// ex: `*.md` matches `readme.md`, `file-?.txt` matches `file-1.txt`
+pub fn matches_glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and the name position it was matched at
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            // Let the last `*` match one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// Terms outside these lengths are not indexed
+const MIN_TERM_LENGTH: usize = 2;
+const MAX_TERM_LENGTH: usize = 64;
+
+// Characters of the content around the first match in a snippet
+const SNIPPET_BEFORE: usize = 60;
+const SNIPPET_AFTER: usize = 140;
+
+// Split text into lowercase words with their byte position in the text
+// ex: `Hello, World!` -> [(0, 'hello'), (7, 'world')]
+pub fn tokenize(text: &str) -> Vec<(usize, String)> {
+    get_terms(text).collect()
+}
+
+// The words of the text one by one, so a scan can stop at the first match
+fn get_terms(text: &str) -> impl Iterator<Item = (usize, String)> + '_ {
+    let mut start: Option<usize> = None;
+
+    text.char_indices()
+        .chain([(text.len(), ' ')])
+        .filter_map(move |(index, c)| match (c.is_alphanumeric(), start) {
+            (true, None) => {
+                start = Some(index);
+                None
+            }
+            (false, Some(_start)) => {
+                start = None;
+                let length = text[_start..index].chars().count();
+                (MIN_TERM_LENGTH..=MAX_TERM_LENGTH)
+                    .contains(&length)
+                    .then(|| (_start, text[_start..index].to_lowercase()))
+            }
+            _ => None,
+        })
+}
+
+// Part of the text around the first occurrence of one of the terms, on a single line
+pub fn get_snippet(text: &str, terms: &[String]) -> String {
+    let position = get_terms(text)
+        .find(|(_, term)| terms.contains(term))
+        .map(|(position, _)| position)
+        .unwrap_or(0);
+
+    let before: Vec<char> = text[..position]
+        .chars()
+        .rev()
+        .take(SNIPPET_BEFORE)
+        .collect();
+    let snippet: String = before
+        .into_iter()
+        .rev()
+        .chain(text[position..].chars().take(SNIPPET_AFTER))
+        .collect();
+    snippet.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_glob_wildcards() {
+        assert!(matches_glob("*.md", "readme.md"));
+        assert!(matches_glob("file-?.txt", "file-1.txt"));
+        assert!(matches_glob("*", ""));
+        assert!(matches_glob("a*b*c", "aXXbYYc"));
+        assert!(matches_glob("*ab", "aab"));
+    }
+
+    #[test]
+    fn matches_glob_mismatches() {
+        assert!(!matches_glob("*.md", "readme.txt"));
+        assert!(!matches_glob("file-?.txt", "file-10.txt"));
+        assert!(!matches_glob("?", ""));
+        assert!(!matches_glob("abc", "ab"));
+    }
+
+    #[test]
+    fn tokenize_positions_and_case() {
+        assert_eq!(
+            tokenize("Hello, World!"),
+            vec![(0, "hello".to_string()), (7, "world".to_string())]
+        );
+    }
+
+    #[test]
+    fn tokenize_term_lengths() {
+        let long = "a".repeat(MAX_TERM_LENGTH + 1);
+        let text = format!("a ab {} ok", long);
+        let terms: Vec<String> = tokenize(&text).into_iter().map(|(_, term)| term).collect();
+        assert_eq!(terms, vec!["ab".to_string(), "ok".to_string()]);
+    }
+
+    #[test]
+    fn tokenize_byte_positions_with_unicode() {
+        let text = "éé word";
+        let terms = tokenize(text);
+        assert_eq!(terms[1], (5, "word".to_string()));
+        assert_eq!(&text[terms[1].0..], "word");
+    }
+
+    #[test]
+    fn snippet_around_first_match() {
+        let text = format!(
+            "{}\nthe needle\n{}",
+            "word ".repeat(100),
+            "rest ".repeat(100)
+        );
+        let snippet = get_snippet(&text, &["needle".to_string()]);
+
+        assert!(snippet.contains("the needle rest"));
+        assert!(!snippet.contains('\n'));
+        assert!(snippet.chars().count() <= SNIPPET_BEFORE + SNIPPET_AFTER);
+    }
+
+    #[test]
+    fn snippet_without_match_starts_at_beginning() {
+        assert_eq!(
+            get_snippet("first line\nsecond", &["x".to_string()]),
+            "first line second"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use unicode_normalization::UnicodeNormalization;
+
+// Split an url into its path segments and query parameters
+// ex: `/directory/file.txt?download` -> (['directory', 'file.txt'], [('download', '')])
+pub fn split_url(url: &str) -> (Vec<&str>, Vec<(&str, &str)>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+
+    let segments: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    let params: Vec<(&str, &str)> = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.split_once('=').unwrap_or((p, "")))
+        .collect();
+
+    (segments, params)
+}
+
+// Decode the percent encoded path segments of an url into a path of the path index
+// ex: ['my%20directory', 'file.txt'] -> `my directory/file.txt`
+pub fn decode_path(segments: &[&str]) -> String {
+    segments
+        .iter()
+        .map(|segment| decode_percent(segment).nfc().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// Decode `%XX` sequences, invalid sequences are kept as they are
+pub fn decode_percent(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn get_query_param<'a>(params: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| *value)
+}
+
+// Percent encode a value as an RFC 5987 `ext-value` (used for `filename*=UTF-8''...`)
+pub fn encode_rfc5987(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// Build a `content-disposition` value that forces a download with the original file name
+// Older clients get an ascii only `filename`, others use the utf-8 encoded `filename*`
+pub fn get_attachment_disposition(file_name: &str) -> String {
+    let fallback: String = file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        fallback,
+        encode_rfc5987(file_name)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_rfc5987_keeps_attr_chars() {
+        assert_eq!(encode_rfc5987("file-1.txt"), "file-1.txt");
+        assert_eq!(encode_rfc5987("a!#$&+^_`|~b"), "a!#$&+^_`|~b");
+    }
+
+    #[test]
+    fn encode_rfc5987_encodes_reserved_and_utf8() {
+        assert_eq!(encode_rfc5987("my file.txt"), "my%20file.txt");
+        assert_eq!(encode_rfc5987("a\"b;c"), "a%22b%3Bc");
+        assert_eq!(encode_rfc5987("é"), "%C3%A9");
+    }
+
+    #[test]
+    fn decode_percent_decodes_sequences() {
+        assert_eq!(decode_percent("my%20file.txt"), "my file.txt");
+        assert_eq!(decode_percent("%C3%A9"), "é");
+        assert_eq!(decode_percent("%2f%2F"), "//");
+    }
+
+    #[test]
+    fn decode_percent_keeps_invalid_sequences() {
+        assert_eq!(decode_percent("100%"), "100%");
+        assert_eq!(decode_percent("%zz"), "%zz");
+        assert_eq!(decode_percent("%2"), "%2");
+    }
+
+    #[test]
+    fn decode_percent_reverses_encode_rfc5987() {
+        let value = "ünïcode name (1).txt";
+        assert_eq!(decode_percent(&encode_rfc5987(value)), value);
+    }
+}
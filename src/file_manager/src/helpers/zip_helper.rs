@@ -0,0 +1,204 @@
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+use crate::models::http_models::ArchiveEntry;
+
+// Uncompressed (STORE) zip archives, see https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+const VERSION: u16 = 20;
+// Bit 3: crc and sizes follow the data in a data descriptor, bit 11: names are utf-8
+const FILE_FLAGS: u16 = 0x0808;
+const DIRECTORY_FLAGS: u16 = 0x0800;
+const DIRECTORY_ATTRIBUTES: u32 = 0x10;
+
+pub const LOCAL_HEADER_SIZE: u64 = 30;
+pub const DATA_DESCRIPTOR_SIZE: u64 = 16;
+pub const CENTRAL_HEADER_SIZE: u64 = 46;
+pub const END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+
+// The 32 bit offsets and 16 bit entry count limit the archive without zip64 extensions
+pub const MAX_ARCHIVE_SIZE: u64 = u32::MAX as u64;
+pub const MAX_ARCHIVE_ENTRIES: usize = u16::MAX as usize;
+
+// Convert a timestamp in nanoseconds to the MS-DOS (time, date) format
+fn get_dos_date_time(nanos: u64) -> (u16, u16) {
+    match NaiveDateTime::from_timestamp_opt((nanos / 1_000_000_000) as i64, 0) {
+        Some(date_time) if date_time.year() >= 1980 => (
+            ((date_time.hour() << 11) | (date_time.minute() << 5) | (date_time.second() / 2))
+                as u16,
+            (((date_time.year() as u32 - 1980) << 9) | (date_time.month() << 5) | date_time.day())
+                as u16,
+        ),
+        // 1980-01-01 00:00:00 is the lowest possible date
+        _ => (0, (1 << 5) | 1),
+    }
+}
+
+pub fn local_header(entry: &ArchiveEntry) -> Vec<u8> {
+    let (time, date) = get_dos_date_time(entry.modified_at);
+    let mut bytes: Vec<u8> = vec![];
+    bytes.extend(LOCAL_HEADER_SIGNATURE.to_le_bytes());
+    bytes.extend(VERSION.to_le_bytes());
+    bytes.extend(get_flags(entry).to_le_bytes());
+    bytes.extend(0u16.to_le_bytes()); // compression method: STORE
+    bytes.extend(time.to_le_bytes());
+    bytes.extend(date.to_le_bytes());
+    // crc and sizes are written in the data descriptor after the data
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend((entry.path.len() as u16).to_le_bytes());
+    bytes.extend(0u16.to_le_bytes()); // extra field length
+    bytes.extend(entry.path.as_bytes());
+    bytes
+}
+
+pub fn data_descriptor(entry: &ArchiveEntry) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![];
+    bytes.extend(DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+    bytes.extend(entry.crc32.to_le_bytes());
+    bytes.extend((entry.size as u32).to_le_bytes());
+    bytes.extend((entry.size as u32).to_le_bytes());
+    bytes
+}
+
+pub fn central_directory(entries: &[ArchiveEntry], offset: u64) -> Vec<u8> {
+    let mut bytes: Vec<u8> = vec![];
+
+    for entry in entries {
+        let (time, date) = get_dos_date_time(entry.modified_at);
+        bytes.extend(CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        bytes.extend(VERSION.to_le_bytes()); // version made by
+        bytes.extend(VERSION.to_le_bytes()); // version needed to extract
+        bytes.extend(get_flags(entry).to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(time.to_le_bytes());
+        bytes.extend(date.to_le_bytes());
+        bytes.extend(entry.crc32.to_le_bytes());
+        bytes.extend((entry.size as u32).to_le_bytes());
+        bytes.extend((entry.size as u32).to_le_bytes());
+        bytes.extend((entry.path.len() as u16).to_le_bytes());
+        bytes.extend(0u16.to_le_bytes()); // extra field length
+        bytes.extend(0u16.to_le_bytes()); // comment length
+        bytes.extend(0u16.to_le_bytes()); // disk number
+        bytes.extend(0u16.to_le_bytes()); // internal attributes
+        let attributes = if entry.file_id.is_none() {
+            DIRECTORY_ATTRIBUTES
+        } else {
+            0
+        };
+        bytes.extend(attributes.to_le_bytes());
+        bytes.extend((entry.offset as u32).to_le_bytes());
+        bytes.extend(entry.path.as_bytes());
+    }
+
+    let central_directory_size = bytes.len() as u32;
+    bytes.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    bytes.extend(0u16.to_le_bytes()); // disk number
+    bytes.extend(0u16.to_le_bytes()); // disk with the central directory
+    bytes.extend((entries.len() as u16).to_le_bytes());
+    bytes.extend((entries.len() as u16).to_le_bytes());
+    bytes.extend(central_directory_size.to_le_bytes());
+    bytes.extend((offset as u32).to_le_bytes());
+    bytes.extend(0u16.to_le_bytes()); // comment length
+    bytes
+}
+
+// Size of the complete archive, used for the `content-length` header
+pub fn get_archive_size(entries: &[ArchiveEntry]) -> u64 {
+    entries
+        .iter()
+        .fold(END_OF_CENTRAL_DIRECTORY_SIZE, |size, entry| {
+            let path_length = entry.path.len() as u64;
+            let descriptor_size = if entry.file_id.is_none() {
+                0
+            } else {
+                DATA_DESCRIPTOR_SIZE
+            };
+            size + LOCAL_HEADER_SIZE
+                + CENTRAL_HEADER_SIZE
+                + 2 * path_length
+                + entry.size
+                + descriptor_size
+        })
+}
+
+fn get_flags(entry: &ArchiveEntry) -> u16 {
+    if entry.file_id.is_none() {
+        DIRECTORY_FLAGS
+    } else {
+        FILE_FLAGS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(file_id: Option<u64>, path: &str, size: u64) -> ArchiveEntry {
+        ArchiveEntry {
+            file_id,
+            version: 0,
+            path: path.to_string(),
+            size,
+            modified_at: 0,
+            crc32: 0,
+            offset: 0,
+        }
+    }
+
+    #[test]
+    fn record_sizes() {
+        let file = entry(Some(1), "directory/file.txt", 5);
+        assert_eq!(
+            local_header(&file).len() as u64,
+            LOCAL_HEADER_SIZE + file.path.len() as u64
+        );
+        assert_eq!(data_descriptor(&file).len() as u64, DATA_DESCRIPTOR_SIZE);
+    }
+
+    #[test]
+    fn archive_size_matches_records() {
+        let entries = vec![
+            entry(None, "directory/", 0),
+            entry(Some(1), "directory/file.txt", 5),
+        ];
+
+        let mut size: u64 = 0;
+        for _entry in &entries {
+            size += local_header(_entry).len() as u64 + _entry.size;
+            if _entry.file_id.is_some() {
+                size += data_descriptor(_entry).len() as u64;
+            }
+        }
+        size += central_directory(&entries, size).len() as u64;
+
+        assert_eq!(get_archive_size(&entries), size);
+    }
+
+    #[test]
+    fn end_of_central_directory() {
+        let entries = vec![entry(Some(1), "a.txt", 1), entry(Some(2), "b.txt", 2)];
+        let bytes = central_directory(&entries, 1234);
+        let end = &bytes[bytes.len() - END_OF_CENTRAL_DIRECTORY_SIZE as usize..];
+
+        assert_eq!(end[0..4], END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        assert_eq!(end[10..12], 2u16.to_le_bytes());
+        assert_eq!(end[16..20], 1234u32.to_le_bytes());
+    }
+
+    #[test]
+    fn dos_date_time() {
+        // Timestamps before 1980 use the lowest possible date
+        assert_eq!(get_dos_date_time(0), (0, (1 << 5) | 1));
+        // 2000-01-01 00:00:00
+        assert_eq!(
+            get_dos_date_time(946_684_800 * 1_000_000_000),
+            (0, (20 << 9) | (1 << 5) | 1)
+        );
+    }
+}
@@ -1,20 +1,47 @@
 pub mod models {
+    pub mod archive_models;
     pub mod asset_models;
     pub mod directory_models;
+    pub mod error_models;
     pub mod file_models;
     pub mod http_models;
+    pub mod listing_models;
+    pub mod metadata_models;
     pub mod misc_models;
+    pub mod ownership_models;
+    pub mod role_models;
+    pub mod search_models;
+    pub mod stable_models;
 }
 
 pub mod logic {
+    pub mod admin_methods;
+    pub mod archive_methods;
     pub mod asset_methods;
     pub mod directory_methods;
     pub mod file_methods;
+    pub mod header_methods;
+    pub mod host_methods;
     pub mod http_methods;
+    pub mod image_methods;
+    pub mod index_methods;
+    pub mod listing_methods;
+    pub mod ownership_methods;
+    pub mod path_methods;
+    pub mod search_methods;
 }
 
 pub mod helpers {
+    pub mod archive_helper;
+    pub mod header_helper;
     pub mod ic_data_helper;
+    pub mod image_helper;
+    pub mod metadata_helper;
+    pub mod mime_helper;
+    pub mod name_helper;
+    pub mod search_helper;
+    pub mod url_helper;
+    pub mod zip_helper;
 }
 
 pub mod methods;
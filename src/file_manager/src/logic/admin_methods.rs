@@ -0,0 +1,126 @@
+use candid::Principal;
+use ic_cdk::caller;
+
+use crate::{
+    models::{error_models::FileManagerError, role_models::Role},
+    store::{Store, STORE},
+};
+
+impl Store {
+    // Make the principal an admin if there are no admins yet (called on install and upgrade)
+    // Only a controller can install or upgrade the canister, so the first admin is a controller
+    pub fn init_admin(principal: Principal) {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            if Self::get_admin_count(&store) == 0 && principal != Principal::anonymous() {
+                store.whitelist.insert(principal, Role::Admin);
+            }
+        })
+    }
+
+    // Existing assets with names that only differ in case are kept, new conflicts are rejected
+    pub fn set_case_insensitive_names(enabled: bool) -> Result<(), FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+
+            if !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+
+            store.case_insensitive_names = enabled;
+            Ok(())
+        })
+    }
+
+    // Grant a role to the principal, replacing its current role
+    pub fn grant_role(principal: Principal, role: Role) -> Result<(), FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+
+            if !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+
+            if principal == Principal::anonymous() {
+                return Err(FileManagerError::InvalidArgument(
+                    "The anonymous principal can not have a role".to_string(),
+                ));
+            }
+
+            if role != Role::Admin && Self::is_last_admin(&principal, &store) {
+                return Err(FileManagerError::InvalidArgument(
+                    "The last admin can not be removed".to_string(),
+                ));
+            }
+
+            store.whitelist.insert(principal, role);
+            Ok(())
+        })
+    }
+
+    pub fn revoke_role(principal: Principal) -> Result<(), FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+
+            if !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+
+            if Self::is_last_admin(&principal, &store) {
+                return Err(FileManagerError::InvalidArgument(
+                    "The last admin can not be removed".to_string(),
+                ));
+            }
+
+            store.whitelist.remove(&principal);
+            Ok(())
+        })
+    }
+
+    pub fn get_roles() -> Result<Vec<(Principal, Role)>, FileManagerError> {
+        STORE.with(|store| {
+            let store = store.borrow();
+
+            if !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+
+            let mut roles: Vec<(Principal, Role)> = store
+                .whitelist
+                .iter()
+                .map(|(principal, role)| (*principal, *role))
+                .collect();
+            roles.sort();
+            Ok(roles)
+        })
+    }
+
+    // The role of the caller, None if it has no role
+    pub fn get_role() -> Option<Role> {
+        STORE.with(|store| store.borrow().whitelist.get(&caller()).copied())
+    }
+
+    pub fn is_admin(principal: &Principal, store: &Store) -> bool {
+        Self::has_role(principal, Role::Admin, store)
+    }
+
+    // Check if the principal has the role or a role that includes it
+    pub fn has_role(principal: &Principal, role: Role, store: &Store) -> bool {
+        store
+            .whitelist
+            .get(principal)
+            .is_some_and(|_role| *_role >= role)
+    }
+
+    fn is_last_admin(principal: &Principal, store: &Store) -> bool {
+        Self::is_admin(principal, store) && Self::get_admin_count(store) == 1
+    }
+
+    fn get_admin_count(store: &Store) -> usize {
+        store
+            .whitelist
+            .values()
+            .filter(|_role| **_role == Role::Admin)
+            .count()
+    }
+}
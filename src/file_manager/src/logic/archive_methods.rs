@@ -0,0 +1,609 @@
+use crc32fast::Hasher;
+use ic_cdk::{api::time, caller, id};
+
+use crate::{
+    helpers::{
+        archive_helper::{get_archive_format, read_archive, ArchiveFile, ChunkReader},
+        header_helper::merge_headers,
+        mime_helper::{get_extension, get_mime_type},
+        name_helper::validate_name,
+        url_helper::{decode_path, get_attachment_disposition},
+        zip_helper::{
+            central_directory, data_descriptor, get_archive_size, local_header,
+            MAX_ARCHIVE_ENTRIES, MAX_ARCHIVE_SIZE,
+        },
+    },
+    models::{
+        archive_models::ArchiveExtraction,
+        asset_models::{Asset, AssetWithId, Id},
+        directory_models::DirectoryEntity,
+        error_models::FileManagerError,
+        file_models::FileEntity,
+        http_models::{
+            ArchiveEntry, ArchiveToken, HeaderField, HttpResponse, StreamingCallbackHttpResponse,
+            StreamingCallbackToken, StreamingStrategy,
+        },
+    },
+    store::{Store, STORE},
+};
+
+// Maximum body size of a single response while streaming an archive, larger chunks are split
+const ARCHIVE_BODY_SIZE: usize = 1_000_000;
+
+// Maximum (estimated) size of the archive entries in a streaming token, the token is sent
+// back with every response so the body and the token stay well below the message size limit
+const MAX_ARCHIVE_TOKEN_SIZE: usize = 256_000;
+
+// Estimated encoded size of an archive entry without its path
+const ARCHIVE_ENTRY_TOKEN_SIZE: usize = 64;
+
+// Chunks of extracted files are kept below the size limit of a single http response
+const EXTRACTED_CHUNK_SIZE: usize = 1_900_000;
+
+// Maximum (uncompressed) size of the files that are extracted in a single call
+const EXTRACT_SIZE_PER_CALL: u64 = 32_000_000;
+
+// Maximum number of entries that are extracted in a single call, every entry updates the indexes
+const EXTRACT_ENTRIES_PER_CALL: usize = 500;
+
+impl Store {
+    // Download a directory and its readable children as (uncompressed) zip archive
+    pub fn get_directory_archive(
+        path: &[&str],
+        root_id: Option<Id>,
+        mut headers: Vec<HeaderField>,
+    ) -> Option<HttpResponse> {
+        STORE.with(|store| {
+            let store = store.borrow();
+            let directory_id = match Self::find_asset_by_path(&decode_path(path), root_id, &store)?
+            {
+                AssetWithId::Directory(directory_id) => directory_id,
+                AssetWithId::File(_) => return None,
+            };
+            let directory = store.directories.get(&directory_id)?;
+            if !Self::is_readable(directory.owner, &directory.permission, caller(), &store) {
+                return None;
+            }
+
+            let mut entries: Vec<ArchiveEntry> = vec![];
+            Self::get_archive_entries(
+                &Self::get_directory_child_assets(directory.id, &store),
+                "",
+                &store,
+                &mut entries,
+            );
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+            let archive_size = get_archive_size(&entries);
+            if entries.len() > MAX_ARCHIVE_ENTRIES
+                || archive_size > MAX_ARCHIVE_SIZE
+                || Self::get_archive_token_size(&entries) > MAX_ARCHIVE_TOKEN_SIZE
+            {
+                return Some(HttpResponse {
+                    status_code: 413,
+                    headers: vec![],
+                    body: "Directory is too large to download as zip archive"
+                        .as_bytes()
+                        .to_vec(),
+                    streaming_strategy: None,
+                });
+            }
+
+            merge_headers(
+                &mut headers,
+                &[
+                    HeaderField("content-type".to_string(), "application/zip".to_string()),
+                    HeaderField("content-length".to_string(), archive_size.to_string()),
+                    HeaderField(
+                        "content-disposition".to_string(),
+                        get_attachment_disposition(&format!("{}.zip", directory.name)),
+                    ),
+                ],
+            );
+
+            let mut archive = ArchiveToken {
+                directory_id: directory.id,
+                entries,
+                entry_index: 0,
+                part_index: 0,
+                chunk_offset: 0,
+                offset: 0,
+            };
+
+            match Self::get_archive_body(&mut archive, &store) {
+                Ok((body, is_complete)) => Some(HttpResponse {
+                    status_code: 200,
+                    body,
+                    streaming_strategy: match is_complete {
+                        true => None,
+                        false => Some(StreamingStrategy::Callback {
+                            callback: candid::Func {
+                                method: "http_request_streaming_callback".to_string(),
+                                principal: id(),
+                            },
+                            token: Self::create_archive_token(archive, &headers),
+                        }),
+                    },
+                    headers,
+                }),
+                Err(err) => Some(HttpResponse {
+                    status_code: 500,
+                    headers: vec![],
+                    body: err.to_string().as_bytes().to_vec(),
+                    streaming_strategy: None,
+                }),
+            }
+        })
+    }
+
+    pub fn http_request_archive_callback(
+        mut archive: ArchiveToken,
+        headers: &[HeaderField],
+    ) -> StreamingCallbackHttpResponse {
+        STORE.with(|store| {
+            let store = store.borrow();
+            match Self::get_archive_body(&mut archive, &store) {
+                Ok((body, is_complete)) => StreamingCallbackHttpResponse {
+                    body,
+                    token: match is_complete {
+                        true => None,
+                        false => Some(Self::create_archive_token(archive, headers)),
+                    },
+                },
+                // A file changed since the download started or is no longer readable,
+                // end the stream instead of mixing content
+                Err(_) => StreamingCallbackHttpResponse {
+                    body: vec![],
+                    token: None,
+                },
+            }
+        })
+    }
+
+    fn create_archive_token(
+        archive: ArchiveToken,
+        headers: &[HeaderField],
+    ) -> StreamingCallbackToken {
+        StreamingCallbackToken {
+            file_id: archive.directory_id,
+            version: 0,
+            variant: None,
+            headers: headers.to_owned(),
+            chunk_index: 0,
+            archive: Some(archive),
+        }
+    }
+
+    fn get_archive_token_size(entries: &[ArchiveEntry]) -> usize {
+        entries
+            .iter()
+            .map(|entry| entry.path.len() + ARCHIVE_ENTRY_TOKEN_SIZE)
+            .sum()
+    }
+
+    // Flatten the readable assets of the directory into archive entries
+    fn get_archive_entries(
+        assets: &[Asset],
+        prefix: &str,
+        store: &Store,
+        entries: &mut Vec<ArchiveEntry>,
+    ) {
+        for asset in assets {
+            match asset {
+                Asset::Directory(_directory) => {
+                    if !Self::is_readable(_directory.owner, &_directory.permission, caller(), store)
+                    {
+                        continue;
+                    }
+
+                    let path = format!("{}{}/", prefix, _directory.name);
+                    entries.push(ArchiveEntry {
+                        file_id: None,
+                        version: 0,
+                        path: path.clone(),
+                        size: 0,
+                        modified_at: _directory.updated_at,
+                        crc32: 0,
+                        offset: 0,
+                    });
+                    Self::get_archive_entries(&_directory.children, &path, store, entries);
+                }
+                Asset::File(_file) => {
+                    if !Self::is_readable(_file.owner, &_file.permission, caller(), store) {
+                        continue;
+                    }
+
+                    entries.push(ArchiveEntry {
+                        file_id: Some(_file.id),
+                        version: _file.version,
+                        path: format!("{}{}", prefix, _file.name),
+                        // Use the size of the uploaded chunks so the archive is always valid
+                        size: _file
+                            .chunks
+                            .iter()
+                            .filter_map(|chunk_id| store.chunks.get(chunk_id))
+                            .map(|bytes| bytes.len() as u64)
+                            .sum(),
+                        modified_at: _file.updated_at,
+                        crc32: 0,
+                        offset: 0,
+                    });
+                }
+            }
+        }
+    }
+
+    // Get the next part of the archive, returns true when the archive is complete
+    fn get_archive_body(
+        archive: &mut ArchiveToken,
+        store: &Store,
+    ) -> Result<(Vec<u8>, bool), FileManagerError> {
+        let mut body: Vec<u8> = vec![];
+
+        while body.len() < ARCHIVE_BODY_SIZE {
+            let entry = match archive.entries.get(archive.entry_index) {
+                Some(_entry) => _entry.clone(),
+                None => {
+                    // All entries are written, end with the central directory
+                    let offset = archive.offset + body.len() as u64;
+                    body.extend(central_directory(&archive.entries, offset));
+                    archive.offset += body.len() as u64;
+                    return Ok((body, true));
+                }
+            };
+
+            let chunks: &[Id] = match entry.file_id {
+                None => &[],
+                Some(file_id) => match store
+                    .files
+                    .get(&file_id)
+                    .filter(|_file| _file.version == entry.version)
+                {
+                    // The token is sent by the client, check every file again
+                    Some(_file)
+                        if !Self::is_readable(_file.owner, &_file.permission, caller(), store) =>
+                    {
+                        return Err(FileManagerError::NotReadable(AssetWithId::File(file_id)))
+                    }
+                    Some(_file) => &_file.chunks,
+                    None => return Err(FileManagerError::ContentChanged(file_id)),
+                },
+            };
+
+            if archive.part_index == 0 {
+                archive.entries[archive.entry_index].offset = archive.offset + body.len() as u64;
+                body.extend(local_header(&entry));
+            } else if archive.part_index <= chunks.len() {
+                let bytes = match store.chunks.get(&chunks[archive.part_index - 1]) {
+                    Some(_bytes) => _bytes,
+                    None => {
+                        return Err(FileManagerError::ChunkNotFound {
+                            file_id: entry.file_id.unwrap_or_default(),
+                            index: (archive.part_index - 1) as u64,
+                        })
+                    }
+                };
+
+                // Send the rest of the chunk in the next response if it does not fit
+                let start = archive.chunk_offset.min(bytes.len());
+                let end = bytes.len().min(start + ARCHIVE_BODY_SIZE - body.len());
+
+                let mut hasher = Hasher::new_with_initial(entry.crc32);
+                hasher.update(&bytes[start..end]);
+                archive.entries[archive.entry_index].crc32 = hasher.finalize();
+                body.extend(&bytes[start..end]);
+
+                if end < bytes.len() {
+                    archive.chunk_offset = end;
+                    break;
+                }
+                archive.chunk_offset = 0;
+            } else {
+                if entry.file_id.is_some() {
+                    body.extend(data_descriptor(&entry));
+                }
+                archive.entry_index += 1;
+                archive.part_index = 0;
+                continue;
+            }
+
+            archive.part_index += 1;
+        }
+
+        archive.offset += body.len() as u64;
+        Ok((body, false))
+    }
+
+    // Extract a zip or tar archive file into the parent directory
+    // Large archives are extracted in parts, call this method again until `is_complete` is true
+    pub fn extract_archive(
+        file_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<ArchiveExtraction, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            Self::check_parent_directory(parent_id, &store)?;
+
+            let file = match store.files.get(&file_id) {
+                None => return Err(FileManagerError::FileNotFound(file_id)),
+                Some(_file) => _file.clone(),
+            };
+
+            if !Self::is_readable(file.owner, &file.permission, caller(), &store) {
+                return Err(FileManagerError::NotReadable(AssetWithId::File(file_id)));
+            }
+
+            if !Self::is_upload_complete(&file, &store) {
+                return Err(FileManagerError::UploadIncomplete(file_id));
+            }
+
+            let format = get_archive_format(&mut Self::get_chunk_reader(&file, &store))
+                .ok_or_else(|| FileManagerError::InvalidArchive {
+                    file_id,
+                    reason: "File is not a zip or tar archive".to_string(),
+                })?;
+            let to_archive_error =
+                |reason: String| FileManagerError::InvalidArchive { file_id, reason };
+
+            // Continue the extraction of the same archive content by the same caller
+            let mut extraction = match store.extractions.get(&file_id) {
+                Some(_extraction)
+                    if _extraction.version == file.version
+                        && _extraction.parent_id == parent_id
+                        && _extraction.owner == caller()
+                        && _extraction.entry_offset.is_some() =>
+                {
+                    _extraction.clone()
+                }
+                _ => {
+                    // Only the entry names are read to replace the existing assets
+                    let archive = read_archive(
+                        Self::get_chunk_reader(&file, &store),
+                        &format,
+                        0,
+                        0,
+                        u64::MAX,
+                        usize::MAX,
+                        false,
+                    )
+                    .map_err(to_archive_error)?;
+                    Self::replace_existing_assets(&archive.files, &file, parent_id, &mut store)?;
+
+                    ArchiveExtraction {
+                        file_id,
+                        version: file.version,
+                        parent_id,
+                        owner: caller(),
+                        entry_index: 0,
+                        entry_offset: Some(0),
+                        entry_count: archive.next_index as u64,
+                        is_complete: false,
+                    }
+                }
+            };
+
+            // Only the entries of this part are decoded, tar archives continue at the entry offset
+            let archive = read_archive(
+                Self::get_chunk_reader(&file, &store),
+                &format,
+                extraction.entry_index as usize,
+                extraction.entry_offset.unwrap_or_default(),
+                EXTRACT_SIZE_PER_CALL,
+                EXTRACT_ENTRIES_PER_CALL,
+                true,
+            )
+            .map_err(to_archive_error)?;
+
+            for archive_file in archive.files {
+                Self::add_archive_file(archive_file, &file, parent_id, &mut store)?;
+            }
+
+            extraction.entry_index = archive.next_index as u64;
+            extraction.entry_offset = Some(archive.next_offset);
+            extraction.is_complete = archive.is_complete;
+
+            if extraction.is_complete {
+                store.extractions.remove(&file_id);
+            } else {
+                store.extractions.insert(file_id, extraction.clone());
+            }
+
+            Ok(extraction)
+        })
+    }
+
+    // Read the content of the archive from its chunks without copying it
+    fn get_chunk_reader<'a>(file: &FileEntity, store: &'a Store) -> ChunkReader<'a> {
+        ChunkReader::new(
+            file.chunks
+                .iter()
+                .filter_map(|chunk_id| store.chunks.get(chunk_id))
+                .map(|bytes| bytes.as_slice())
+                .collect(),
+        )
+    }
+
+    // Same as `add_assets`, existing files and directories with the name of a top level entry are replaced
+    // The archive itself and the directories it is in are kept
+    fn replace_existing_assets(
+        archive_files: &[ArchiveFile],
+        archive: &FileEntity,
+        parent_id: Option<Id>,
+        store: &mut Store,
+    ) -> Result<(), FileManagerError> {
+        let names: Vec<&String> = archive_files
+            .iter()
+            .map(|archive_file| &archive_file.path[0])
+            .collect();
+        let is_replaced = |name: &str| names.iter().any(|n| Self::is_same_name(n, name, store));
+
+        let existing_files: Vec<FileEntity> = Self::get_child_files(parent_id, store)
+            .into_iter()
+            .filter(|_file| _file.id != archive.id && is_replaced(&_file.name))
+            .cloned()
+            .collect();
+
+        let existing_directories: Vec<DirectoryEntity> =
+            Self::get_child_directories(parent_id, store)
+                .into_iter()
+                .filter(|_directory| {
+                    is_replaced(&_directory.name)
+                        && !Self::is_inside_directory(archive.parent_id, _directory.id, store)
+                })
+                .cloned()
+                .collect();
+
+        let mut protected_assets: Vec<AssetWithId> = vec![];
+        for existing_file in existing_files {
+            if Self::_delete_file(existing_file.id, store).is_err() {
+                protected_assets.push(AssetWithId::File(existing_file.id));
+            }
+        }
+
+        for existing_directory in existing_directories {
+            if Self::_delete_directory(existing_directory.id, store).is_err() {
+                protected_assets.push(AssetWithId::Directory(existing_directory.id));
+            }
+        }
+
+        if !protected_assets.is_empty() {
+            return Err(FileManagerError::ProtectedAssets(protected_assets));
+        }
+
+        Ok(())
+    }
+
+    // Add an extracted file or directory, missing directories in its path are created
+    fn add_archive_file(
+        archive_file: ArchiveFile,
+        archive: &FileEntity,
+        parent_id: Option<Id>,
+        store: &mut Store,
+    ) -> Result<(), FileManagerError> {
+        let permission = &archive.permission;
+        // Entries with a reserved name are skipped like other invalid entries
+        if validate_name(
+            &archive_file.path[0],
+            Self::is_document_root(parent_id, store),
+        )
+        .is_err()
+        {
+            return Ok(());
+        }
+
+        let (name, directory_path) = match archive_file.is_directory {
+            true => (None, &archive_file.path[..]),
+            false => match archive_file.path.split_last() {
+                Some((_name, _directory_path)) => (Some(_name), _directory_path),
+                None => return Ok(()),
+            },
+        };
+
+        let mut directory_id = parent_id;
+        for directory_name in directory_path {
+            directory_id = match Self::get_or_create_archive_directory(
+                directory_name,
+                directory_id,
+                archive,
+                store,
+            )? {
+                Some(_directory_id) => Some(_directory_id),
+                // The archive itself has the name of the directory
+                None => return Ok(()),
+            };
+        }
+
+        let name = match name {
+            Some(_name) => _name.clone(),
+            None => return Ok(()),
+        };
+
+        // A directory takes precedence over a file with the same name
+        if Self::find_child_directory(directory_id, &name, store).is_some() {
+            return Ok(());
+        }
+
+        // Files that occur multiple times in the archive are replaced by the last one
+        if let Some(existing_file) = Self::find_child_file(directory_id, &name, store).cloned() {
+            if existing_file.id == archive.id {
+                return Ok(());
+            }
+            Self::_delete_file(existing_file.id, store)?;
+        }
+
+        let mut chunks: Vec<Id> = vec![];
+        for bytes in archive_file.bytes.chunks(EXTRACTED_CHUNK_SIZE) {
+            let chunk_id = store.chunk_id;
+            store.chunks.insert(chunk_id, bytes.to_vec());
+            chunks.push(chunk_id);
+            store.chunk_id += 1;
+        }
+
+        let file_id = store.file_id;
+        let extension = get_extension(&name).to_string();
+        let file = FileEntity {
+            id: file_id,
+            mime_type: get_mime_type(&extension).to_string(),
+            extension,
+            name,
+            size: archive_file.bytes.len() as u64,
+            permission: permission.clone(),
+            parent_id: directory_id,
+            chunks,
+            version: 0,
+            variants: vec![],
+            metadata: Default::default(),
+            headers: vec![],
+            disposition: Default::default(),
+            created_at: time(),
+            updated_at: time(),
+            is_protected: false,
+            owner: Some(caller()),
+        };
+        store.files.insert(file_id, file.clone());
+        Self::index_file(&file, store);
+        store.file_id += 1;
+
+        Self::commit_file(file_id, store);
+        Ok(())
+    }
+
+    // Returns None if the archive has the name of the directory, the archive is never replaced
+    fn get_or_create_archive_directory(
+        name: &str,
+        parent_id: Option<Id>,
+        archive: &FileEntity,
+        store: &mut Store,
+    ) -> Result<Option<Id>, FileManagerError> {
+        if let Some(_directory) = Self::find_child_directory(parent_id, name, store) {
+            return Ok(Some(_directory.id));
+        }
+
+        // Replace a file with the same name that was extracted before
+        if let Some(existing_file) = Self::find_child_file(parent_id, name, store).cloned() {
+            if existing_file.id == archive.id {
+                return Ok(None);
+            }
+            Self::_delete_file(existing_file.id, store)?;
+        }
+
+        let directory_id = store.directory_id;
+        let directory = DirectoryEntity {
+            id: directory_id,
+            name: name.to_string(),
+            parent_id,
+            permission: archive.permission.clone(),
+            headers: vec![],
+            header_rules: vec![],
+            metadata: Default::default(),
+            created_at: time(),
+            updated_at: time(),
+            is_protected: false,
+            owner: Some(caller()),
+        };
+        store.directories.insert(directory_id, directory.clone());
+        Self::index_directory(&directory, store);
+        store.directory_id += 1;
+        Ok(Some(directory_id))
+    }
+}
@@ -1,40 +1,95 @@
+use std::mem::take;
+
 use candid::Principal;
 use ic_cdk::{api::time, caller};
 
 use crate::{
+    helpers::{
+        header_helper::{get_preset_headers, merge_headers, validate_headers},
+        metadata_helper::validate_metadata,
+        mime_helper::{get_extension, resolve_mime_type},
+        name_helper::validate_name,
+    },
     models::{
-        asset_models::{Asset, AssetWithId, Id, NestedAssets, Permission, PostAsset},
-        directory_models::{DirectoryEntity, DirectoryResponse},
+        asset_models::{
+            AddAssetsResult, Asset, AssetWithId, Id, NestedAssets, Permission, PostAsset,
+        },
+        directory_models::DirectoryEntity,
+        error_models::FileManagerError,
         file_models::{FileEntity, FileResponse},
+        http_models::{HeaderField, HeaderPreset},
+        metadata_models::MetadataChange,
+        role_models::Role,
     },
     store::{Store, STORE},
 };
 
 impl Store {
-    pub fn add_assets(
-        parent_id: Option<Id>,
-        assets: Vec<NestedAssets>,
-    ) -> Result<Vec<(FileResponse, String)>, (Vec<Asset>, String)> {
+    pub fn add_assets(parent_id: Option<Id>, mut assets: Vec<NestedAssets>) -> AddAssetsResult {
+        let (is_document_root, case_insensitive) = STORE.with(|store| {
+            let store = store.borrow();
+            (
+                Self::is_document_root(parent_id, &store),
+                store.case_insensitive_names,
+            )
+        });
+        Self::validate_nested_assets(&mut assets, is_document_root, case_insensitive)?;
+
         STORE.with(|store| {
             let mut store = store.borrow_mut();
             // check if the files / directories are protected or owned by the caller
-            if let Some(_parent_id) = parent_id {
-                if let Some(_directory) = store.directories.get(&_parent_id) {
-                    if _directory.is_protected {
-                        return Err((vec![], format!("Directory {} is protected", _parent_id)));
-                    }
+            Self::check_parent_directory(parent_id, &store)?;
 
-                    if _directory.owner != Some(caller()) {
-                        return Err((
-                            vec![],
-                            format!("Directory {} is not owned by you", _parent_id),
-                        ));
+            Self::add_assets_recursive(parent_id, assets, &mut store, false)
+        })
+    }
+
+    // Check the posted assets before anything is added to the store, names are normalized in place
+    fn validate_nested_assets(
+        assets: &mut [NestedAssets],
+        is_document_root: bool,
+        case_insensitive: bool,
+    ) -> Result<(), FileManagerError> {
+        let mut names: Vec<String> = vec![];
+        for nested_asset in assets {
+            let name = match &mut nested_asset.asset {
+                PostAsset::File(post_file) => {
+                    post_file.name = validate_name(&post_file.name, is_document_root)?;
+                    post_file.metadata = validate_metadata(take(&mut post_file.metadata))?;
+                    // Empty files are allowed without chunks, other files need at least one
+                    if post_file.chunk_count == 0 && post_file.size > 0 {
+                        return Err(FileManagerError::InvalidArgument(format!(
+                            "File {} has no chunks",
+                            post_file.name
+                        )));
                     }
+                    &post_file.name
+                }
+                PostAsset::Directory(post_directory) => {
+                    post_directory.name = validate_name(&post_directory.name, is_document_root)?;
+                    post_directory.metadata =
+                        validate_metadata(take(&mut post_directory.metadata))?;
+                    &post_directory.name
                 }
+                PostAsset::None => continue,
+            };
+
+            // Siblings can not share a name, also not between files and directories
+            let key = match case_insensitive {
+                true => name.to_lowercase(),
+                false => name.clone(),
+            };
+            if names.contains(&key) {
+                return Err(FileManagerError::InvalidArgument(format!(
+                    "Asset with name {} is added more than once",
+                    name
+                )));
             }
+            names.push(key);
 
-            Self::add_assets_recursive(parent_id, assets, &mut store, false)
-        })
+            Self::validate_nested_assets(&mut nested_asset.children, false, case_insensitive)?;
+        }
+        Ok(())
     }
 
     // Add multiple files and directories to the store defined by the Asset type
@@ -43,61 +98,38 @@ impl Store {
         assets: Vec<NestedAssets>,
         store: &mut Store,
         is_protected: bool,
-    ) -> Result<Vec<(FileResponse, String)>, (Vec<Asset>, String)> {
+    ) -> AddAssetsResult {
         // Initialize an empty files vector and origin path on the user his file system to return
         let mut files: Vec<(FileResponse, String)> = vec![];
-        let mut protected_assets: Vec<Asset> = vec![];
+        let mut protected_assets: Vec<AssetWithId> = vec![];
 
         for nested_asset in assets.clone() {
-            match nested_asset.asset {
-                PostAsset::File(post_file) => {
-                    // If the file already exists, remove the existing file and its chunks
-                    if let Some(existing_file) = store.files.clone().values().find(|_exisiting| {
-                        _exisiting.parent_id == parent_id && _exisiting.name == post_file.name
-                    }) {
-                        match Self::_delete_file(existing_file.id, store) {
-                            Ok(_) => {}
-                            Err(_) => {
-                                protected_assets.push(Asset::File(
-                                    Self::map_file_entity_to_file_response(
-                                        existing_file.clone(),
-                                        store,
-                                    ),
-                                ));
-                            }
-                        }
-                    }
+            let name = match nested_asset.asset {
+                PostAsset::File(post_file) => post_file.name,
+                PostAsset::Directory(post_directory) => post_directory.name,
+                PostAsset::None => continue,
+            };
+
+            // If a file or directory with the same name already exists, remove it and its content
+            if let Some(existing_file) = Self::find_child_file(parent_id, &name, store).cloned() {
+                match Self::_delete_file(existing_file.id, store) {
+                    Ok(_) => {}
+                    Err(_) => protected_assets.push(AssetWithId::File(existing_file.id)),
                 }
+            }
 
-                PostAsset::Directory(post_directory) => {
-                    if let Some(existing_directory) =
-                        store.directories.clone().values().find(|_exisiting| {
-                            _exisiting.parent_id == parent_id
-                                && _exisiting.name == post_directory.name
-                        })
-                    {
-                        match Self::_delete_directory(existing_directory.id, store) {
-                            Ok(_) => {}
-                            Err(_) => {
-                                protected_assets.push(Asset::Directory(
-                                    Self::map_directory_entity_to_directory_response(
-                                        existing_directory.clone(),
-                                        &store,
-                                    ),
-                                ));
-                            }
-                        }
-                    }
+            if let Some(existing_directory) =
+                Self::find_child_directory(parent_id, &name, store).cloned()
+            {
+                match Self::_delete_directory(existing_directory.id, store) {
+                    Ok(_) => {}
+                    Err(_) => protected_assets.push(AssetWithId::Directory(existing_directory.id)),
                 }
-                PostAsset::None => {}
             }
         }
 
         if !protected_assets.is_empty() && !is_protected {
-            return Err((
-                protected_assets,
-                "Some assets are protected or not owned by you".to_string(),
-            ));
+            return Err(FileManagerError::ProtectedAssets(protected_assets));
         }
 
         // Iterate over the assets
@@ -108,17 +140,25 @@ impl Store {
                     // get the file id
                     let file_id = store.file_id;
 
+                    // The extension and mime type sent by the client are not trusted
+                    let extension = get_extension(&post_file.name).to_string();
+                    let mime_type = resolve_mime_type(&extension, &post_file.mime_type);
+
                     // Create the file entry from post_file
                     let mut file = FileEntity {
                         id: file_id,
                         name: post_file.name.clone(),
                         size: post_file.size,
-                        mime_type: post_file.mime_type,
-                        extension: post_file.extension,
+                        mime_type,
+                        extension,
                         permission: post_file.permission,
                         parent_id,
                         chunks: vec![],
+                        version: 0,
+                        variants: vec![],
                         metadata: post_file.metadata,
+                        headers: vec![],
+                        disposition: Default::default(),
                         created_at: time(),
                         updated_at: time(),
                         is_protected,
@@ -135,13 +175,22 @@ impl Store {
 
                     // Insert the file into the store
                     store.files.insert(file_id, file.clone());
+                    Self::index_file(&file, store);
 
                     // Increment the file id
                     store.file_id += 1;
 
+                    // Empty files receive no chunks, their upload is complete right away
+                    if file.chunks.is_empty() {
+                        Self::commit_file(file_id, store);
+                        if let Some(_file) = store.files.get(&file_id) {
+                            file = _file.clone();
+                        }
+                    }
+
                     // Map the file entity to a file response and push it to the files vector
                     files.push((
-                        Self::map_file_entity_to_file_response(file, &store),
+                        Self::map_file_entity_to_file_response(file, store),
                         post_file.origin_path,
                     ));
                 }
@@ -155,6 +204,9 @@ impl Store {
                         name: post_directory.name.clone(),
                         parent_id,
                         permission: post_directory.permission,
+                        headers: vec![],
+                        header_rules: vec![],
+                        metadata: post_directory.metadata,
                         created_at: time(),
                         updated_at: time(),
                         is_protected,
@@ -162,7 +214,8 @@ impl Store {
                     };
 
                     // Insert the directory into the store
-                    store.directories.insert(directory_id, directory);
+                    store.directories.insert(directory_id, directory.clone());
+                    Self::index_directory(&directory, store);
 
                     // Increment the directory id
                     store.directory_id += 1;
@@ -190,114 +243,109 @@ impl Store {
     }
 
     // Get all files and directories in a tree structure (parent -> children)
-    pub fn get_assets_tree(parent_id: Option<Id>, owner: Option<Principal>) -> Vec<Asset> {
+    // The depth limits the levels of children, ex: 1 only returns the direct children
+    pub fn get_assets_tree(
+        parent_id: Option<Id>,
+        owner: Option<Principal>,
+        max_depth: Option<u32>,
+    ) -> Vec<Asset> {
+        STORE.with(|store| Self::get_assets_recursive(parent_id, owner, max_depth, &store.borrow()))
+    }
+
+    // Get the direct children of the directory (root if None), child directories only include their counts
+    pub fn list_directory(directory_id: Option<Id>) -> Result<Vec<Asset>, FileManagerError> {
         STORE.with(|store| {
             let store = store.borrow();
-            // Get all directories filtered by parent_id (root is None)
-            let mut directories: Vec<DirectoryResponse> = store
-                .directories
-                .values()
-                .filter(|_dir| {
-                    if let Some(owner) = owner {
-                        _dir.owner == Some(owner)
-                    } else {
-                        true
-                    }
-                })
-                // Map the entities to the correct response
-                .map(|d| Self::map_directory_entity_to_directory_response(d.clone(), &store))
-                .collect();
+            if let Some(_directory_id) = directory_id {
+                if !store.directories.contains_key(&_directory_id) {
+                    return Err(FileManagerError::DirectoryNotFound(_directory_id));
+                }
+            }
+            Ok(Self::get_assets_recursive(
+                directory_id,
+                None,
+                Some(1),
+                &store,
+            ))
+        })
+    }
 
-            // Get all child assets (files and directories) for the directory
-            Self::get_assets_recursive(parent_id, &mut directories, owner, &store)
+    // Get a single file or directory without its children
+    pub fn stat(asset: AssetWithId) -> Result<Asset, FileManagerError> {
+        STORE.with(|store| {
+            let store = store.borrow();
+            Self::get_asset_response(&asset, &store)
         })
     }
 
+    pub fn get_asset_response(
+        asset: &AssetWithId,
+        store: &Store,
+    ) -> Result<Asset, FileManagerError> {
+        match asset {
+            AssetWithId::File(file_id) => match store.files.get(file_id) {
+                Some(_file) => Ok(Asset::File(Self::map_file_entity_to_file_response(
+                    _file.clone(),
+                    store,
+                ))),
+                None => Err(FileManagerError::FileNotFound(*file_id)),
+            },
+            AssetWithId::Directory(directory_id) => match store.directories.get(directory_id) {
+                Some(_directory) => Ok(Asset::Directory(
+                    Self::map_directory_entity_to_directory_response(_directory.clone(), store),
+                )),
+                None => Err(FileManagerError::DirectoryNotFound(*directory_id)),
+            },
+        }
+    }
+
+    // Get the child assets (files and directories) of the directory, filtered by owner if provided
     fn get_assets_recursive(
         parent_id: Option<Id>,
-        directories: &mut Vec<DirectoryResponse>,
         owner: Option<Principal>,
+        max_depth: Option<u32>,
         store: &Store,
     ) -> Vec<Asset> {
-        // Find all directories with the given parent_id
-        let mut _directories: Vec<DirectoryResponse> = directories
-            .iter()
-            .filter(|_dir| {
-                if let Some(owner) = owner {
-                    _dir.owner == Some(owner) && _dir.parent_id == parent_id
-                } else {
-                    _dir.parent_id == parent_id
-                }
-            })
-            .cloned()
-            .collect();
-
-        // Find all files with the given parent_id
-        let _files: Vec<FileEntity> = STORE.with(|store| {
-            store
-                .borrow()
-                .files
-                .values()
-                .filter(|_file| {
-                    if let Some(owner) = owner {
-                        _file.owner == Some(owner) && _file.parent_id == parent_id
-                    } else {
-                        _file.parent_id == parent_id
-                    }
-                })
-                .cloned()
-                .collect()
-        });
-
-        // Recursively get children of each directory
-        for child in &mut _directories {
-            child.children = Self::get_assets_recursive(Some(child.id), directories, owner, store)
+        if max_depth == Some(0) {
+            return vec![];
         }
 
-        // Convert directories to assets and add them to the assets vec
-        let mut assets: Vec<Asset> = _directories
-            .into_iter()
-            .map(|a| Asset::Directory(a.clone()))
-            .collect();
-
-        // Convert files to assets and add them to the assets vec
-        _files.iter().for_each(|a| {
-            assets.push(Asset::File(Self::map_file_entity_to_file_response(
-                a.clone(),
-                &store,
-            )))
-        });
-
-        assets
-    }
-
-    pub fn get_directory_child_assets(parent_id: Id, store: &Store) -> Vec<Asset> {
+        let is_owned = |asset_owner: Option<Principal>| owner.is_none() || asset_owner == owner;
         let mut assets: Vec<Asset> = vec![];
 
-        store
-            .directories
-            .values()
-            .filter(|dir| Some(parent_id) == dir.parent_id)
-            .for_each(|_dir| {
-                assets.push(Asset::Directory(
-                    Self::map_directory_entity_to_directory_response(_dir.clone(), &store),
-                ))
-            });
+        for _directory in Self::get_child_directories(parent_id, store) {
+            if is_owned(_directory.owner) {
+                let children = Self::get_assets_recursive(
+                    Some(_directory.id),
+                    owner,
+                    max_depth.map(|depth| depth - 1),
+                    store,
+                );
+                assets.push(Asset::Directory(Self::map_directory_entity(
+                    _directory.clone(),
+                    children,
+                    store,
+                )));
+            }
+        }
 
-        store
-            .files
-            .values()
-            .filter(|file| Some(parent_id) == file.parent_id)
-            .for_each(|_file| {
+        for _file in Self::get_child_files(parent_id, store) {
+            if is_owned(_file.owner) {
                 assets.push(Asset::File(Self::map_file_entity_to_file_response(
                     _file.clone(),
-                    &store,
-                )))
-            });
+                    store,
+                )));
+            }
+        }
+
         assets
     }
 
-    pub fn change_asset_name(name: String, asset: AssetWithId) -> Result<Asset, String> {
+    pub fn get_directory_child_assets(parent_id: Id, store: &Store) -> Vec<Asset> {
+        Self::get_assets_recursive(Some(parent_id), None, None, store)
+    }
+
+    pub fn change_asset_name(name: String, asset: AssetWithId) -> Result<Asset, FileManagerError> {
         match asset {
             AssetWithId::File(file_id) => match Self::change_file_name(file_id, name) {
                 Ok(file) => Ok(Asset::File(file)),
@@ -315,7 +363,7 @@ impl Store {
     pub fn change_asset_permission(
         permission: Permission,
         asset: AssetWithId,
-    ) -> Result<Asset, String> {
+    ) -> Result<Asset, FileManagerError> {
         match asset {
             AssetWithId::File(file_id) => match Self::change_file_permission(file_id, permission) {
                 Ok(file) => Ok(Asset::File(file)),
@@ -330,7 +378,53 @@ impl Store {
         }
     }
 
-    pub fn change_asset_parent(parent_id: Option<Id>, asset: AssetWithId) -> Result<Asset, String> {
+    pub fn change_asset_headers(
+        headers: Vec<HeaderField>,
+        preset: Option<HeaderPreset>,
+        asset: AssetWithId,
+    ) -> Result<Asset, FileManagerError> {
+        // Start from the preset headers, the given headers take precedence
+        let mut _headers = preset
+            .map(|_preset| get_preset_headers(&_preset))
+            .unwrap_or_default();
+        merge_headers(&mut _headers, &validate_headers(headers)?);
+
+        match asset {
+            AssetWithId::File(file_id) => match Self::change_file_headers(file_id, _headers) {
+                Ok(file) => Ok(Asset::File(file)),
+                Err(err) => Err(err),
+            },
+            AssetWithId::Directory(directory_id) => {
+                match Self::change_directory_headers(directory_id, _headers) {
+                    Ok(directory) => Ok(Asset::Directory(directory)),
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    pub fn change_asset_metadata(
+        change: MetadataChange,
+        asset: AssetWithId,
+    ) -> Result<Asset, FileManagerError> {
+        match asset {
+            AssetWithId::File(file_id) => match Self::change_file_metadata(file_id, change) {
+                Ok(file) => Ok(Asset::File(file)),
+                Err(err) => Err(err),
+            },
+            AssetWithId::Directory(directory_id) => {
+                match Self::change_directory_metadata(directory_id, change) {
+                    Ok(directory) => Ok(Asset::Directory(directory)),
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    pub fn change_asset_parent(
+        parent_id: Option<Id>,
+        asset: AssetWithId,
+    ) -> Result<Asset, FileManagerError> {
         match asset {
             AssetWithId::File(file_id) => match Self::change_file_parent(file_id, parent_id) {
                 Ok(file) => Ok(Asset::File(file)),
@@ -345,7 +439,76 @@ impl Store {
         }
     }
 
-    pub fn delete_asset(asset: AssetWithId) -> Result<(), String> {
+    pub fn copy_asset(
+        parent_id: Option<Id>,
+        asset: AssetWithId,
+    ) -> Result<Asset, FileManagerError> {
+        match asset {
+            AssetWithId::File(file_id) => match Self::copy_file(file_id, parent_id) {
+                Ok(file) => Ok(Asset::File(file)),
+                Err(err) => Err(err),
+            },
+            AssetWithId::Directory(directory_id) => {
+                match Self::copy_directory(directory_id, parent_id) {
+                    Ok(directory) => Ok(Asset::Directory(directory)),
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+
+    // Compare the names of sibling assets, case insensitive if enabled by an admin
+    pub fn is_same_name(name: &str, other_name: &str, store: &Store) -> bool {
+        match store.case_insensitive_names {
+            true => name.to_lowercase() == other_name.to_lowercase(),
+            false => name == other_name,
+        }
+    }
+
+    // Files and directories in the same directory can not share a name
+    // The asset itself is ignored when it is renamed or moved
+    pub fn check_sibling_name(
+        name: &str,
+        parent_id: Option<Id>,
+        asset: Option<&AssetWithId>,
+        store: &Store,
+    ) -> Result<(), FileManagerError> {
+        let file_exists = Self::get_child_files(parent_id, store).iter().any(|_file| {
+            !matches!(asset, Some(AssetWithId::File(id)) if *id == _file.id)
+                && Self::is_same_name(&_file.name, name, store)
+        });
+
+        let directory_exists =
+            Self::get_child_directories(parent_id, store)
+                .iter()
+                .any(|_directory| {
+                    !matches!(asset, Some(AssetWithId::Directory(id)) if *id == _directory.id)
+                        && Self::is_same_name(&_directory.name, name, store)
+                });
+
+        if file_exists || directory_exists {
+            return Err(FileManagerError::NameConflict {
+                name: name.to_string(),
+                parent_id,
+            });
+        }
+        Ok(())
+    }
+
+    // Assets can be read by their owner, public assets can be read by anyone
+    // Readers can read the private assets of others
+    pub fn is_readable(
+        owner: Option<Principal>,
+        permission: &Permission,
+        principal: Principal,
+        store: &Store,
+    ) -> bool {
+        owner == Some(principal)
+            || matches!(permission, Permission::Public)
+            || Self::has_role(&principal, Role::Reader, store)
+    }
+
+    pub fn delete_asset(asset: AssetWithId) -> Result<(), FileManagerError> {
         match asset {
             AssetWithId::File(file_id) => match Self::delete_file(file_id) {
                 Ok(_) => Ok(()),
@@ -1,88 +1,149 @@
-use candid::Principal;
-use ic_cdk::{
-    api::{call, time},
-    caller,
-};
+use ic_cdk::{api::time, caller};
 
 use crate::{
+    helpers::{metadata_helper::apply_metadata_change, name_helper::validate_name},
     models::{
-        asset_models::{Id, Permission},
-        directory_models::{DirectoryEntity, DirectoryResponse},
+        asset_models::{Asset, AssetWithId, Id, Permission},
+        directory_models::{DirectoryEntity, DirectoryResponse, DirectoryStats},
+        error_models::FileManagerError,
         file_models::FileEntity,
+        http_models::{HeaderField, HeaderRule},
+        metadata_models::MetadataChange,
+        misc_models::StorageUsage,
+        role_models::Role,
     },
     store::{Store, STORE},
 };
 
 impl Store {
-    pub fn delete_directory(directory_id: u64) -> Result<(), String> {
+    pub fn delete_directory(directory_id: u64) -> Result<(), FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
             Self::_delete_directory(directory_id, &mut store)
         })
     }
 
-    // This method happens recursively
-    pub fn _delete_directory(directory_id: u64, store: &mut Store) -> Result<(), String> {
-        let directories = store.directories.clone();
-        let files = store.files.clone();
+    // The whole tree is checked first so a directory is never removed with children left behind
+    pub fn _delete_directory(directory_id: u64, store: &mut Store) -> Result<(), FileManagerError> {
+        let is_admin = Self::is_admin(&caller(), store);
+        Self::check_directory_tree(directory_id, is_admin, store)?;
+        Self::remove_directory_tree(directory_id, store)
+    }
+
+    // Check that every directory and file in the tree can be deleted by the caller
+    fn check_directory_tree(
+        directory_id: Id,
+        is_admin: bool,
+        store: &mut Store,
+    ) -> Result<(), FileManagerError> {
         let directory = store.directories.get_mut(&directory_id);
-        match Self::check_directory_state(directory) {
-            Err(err) => Err(err),
-            Ok(_directory) => {
-                let children: Vec<&DirectoryEntity> = directories
-                    .values()
-                    .filter(|child_dir| child_dir.parent_id == Some(_directory.id))
-                    .collect();
-
-                let files: Vec<&FileEntity> = files
-                    .values()
-                    .filter(|_file| _file.parent_id == Some(_directory.id))
-                    .collect();
-
-                for child in children {
-                    // ignore the result of the recursive call
-                    let _ = Self::_delete_directory(child.id, store);
-                }
+        Self::check_directory_state(directory, directory_id, is_admin)?;
 
-                for child in files {
-                    // ignore the result of the recursive call
-                    let _ = Self::_delete_file(child.id, store);
-                }
+        let children: Vec<Id> = Self::get_child_directories(Some(directory_id), store)
+            .iter()
+            .map(|child_dir| child_dir.id)
+            .collect();
 
-                store.directories.remove(&directory_id);
-                Ok(())
-            }
+        let files: Vec<Id> = Self::get_child_files(Some(directory_id), store)
+            .iter()
+            .map(|_file| _file.id)
+            .collect();
+
+        for child_id in children {
+            Self::check_directory_tree(child_id, is_admin, store)?;
+        }
+
+        for file_id in files {
+            let file = store.files.get_mut(&file_id);
+            Self::check_file_state(file, file_id, is_admin)?;
+        }
+        Ok(())
+    }
+
+    // This method happens recursively
+    fn remove_directory_tree(directory_id: Id, store: &mut Store) -> Result<(), FileManagerError> {
+        let directory = match store.directories.get(&directory_id) {
+            Some(_directory) => _directory.clone(),
+            None => return Err(FileManagerError::DirectoryNotFound(directory_id)),
+        };
+
+        let children: Vec<Id> = Self::get_child_directories(Some(directory_id), store)
+            .iter()
+            .map(|child_dir| child_dir.id)
+            .collect();
+
+        let files: Vec<Id> = Self::get_child_files(Some(directory_id), store)
+            .iter()
+            .map(|_file| _file.id)
+            .collect();
+
+        for child_id in children {
+            Self::remove_directory_tree(child_id, store)?;
         }
+
+        for child_id in files {
+            Self::_delete_file(child_id, store)?;
+        }
+
+        Self::unindex_directory(&directory, store);
+        store.directories.remove(&directory_id);
+        store.directory_stats.remove(&directory_id);
+        store
+            .transfers
+            .remove(&AssetWithId::Directory(directory_id));
+        // Requests for hosts mapped to this directory fall back to the root
+        store
+            .hosts
+            .retain(|_, _directory_id| *_directory_id != directory_id);
+        Ok(())
     }
 
     pub fn change_directory_name(
         directory_id: Id,
         name: String,
-    ) -> Result<DirectoryResponse, String> {
+    ) -> Result<DirectoryResponse, FileManagerError> {
         STORE.with(|store| {
-            let invalid_chars = ["/", "*", "\\", ":", "?", "\"", "<", ">", "'"];
+            let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
+            let directory = store.directories.get_mut(&directory_id);
+            let previous = Self::check_directory_state(directory, directory_id, is_admin)?.clone();
 
-            if invalid_chars.iter().any(|&c| name.contains(c)) {
-                return Err("Invalid directory name".to_string());
-            }
+            // Names are checked after the permission, the names of other assets can not be probed
+            let name = validate_name(&name, Self::is_document_root(previous.parent_id, &store))?;
+            Self::check_sibling_name(
+                &name,
+                previous.parent_id,
+                Some(&AssetWithId::Directory(directory_id)),
+                &store,
+            )?;
 
-            let mut store = store.borrow_mut();
-            let directories = store.directories.clone();
+            let directory = DirectoryEntity {
+                name,
+                updated_at: time(),
+                ..previous.clone()
+            };
+            store.directories.insert(directory_id, directory.clone());
 
-            if directories.iter().any(|(_id, directory)| {
-                directory.parent_id == Some(directory_id) && directory.name == name
-            }) {
-                return Err("Directory with same name already exists".to_string());
-            }
+            Self::reindex_directory(&previous, &directory, &mut store);
+            Ok(Self::map_directory_entity_to_directory_response(
+                directory, &store,
+            ))
+        })
+    }
 
+    pub fn change_directory_permission(
+        directory_id: Id,
+        permission: Permission,
+    ) -> Result<DirectoryResponse, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
             let directory = store.directories.get_mut(&directory_id);
-
-            match Self::check_directory_state(directory) {
+            match Self::check_directory_state(directory, directory_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_directory) => {
-                    _directory.name = name;
+                    _directory.permission = permission;
                     _directory.updated_at = time();
-
                     Ok(Self::map_directory_entity_to_directory_response(
                         _directory.clone(),
                         &store,
@@ -92,17 +153,18 @@ impl Store {
         })
     }
 
-    pub fn change_directory_permission(
+    pub fn change_directory_headers(
         directory_id: Id,
-        permission: Permission,
-    ) -> Result<DirectoryResponse, String> {
+        headers: Vec<HeaderField>,
+    ) -> Result<DirectoryResponse, FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
             let directory = store.directories.get_mut(&directory_id);
-            match Self::check_directory_state(directory) {
+            match Self::check_directory_state(directory, directory_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_directory) => {
-                    _directory.permission = permission;
+                    _directory.headers = headers;
                     _directory.updated_at = time();
                     Ok(Self::map_directory_entity_to_directory_response(
                         _directory.clone(),
@@ -113,28 +175,18 @@ impl Store {
         })
     }
 
-    pub fn change_directory_parent(
+    pub fn change_directory_metadata(
         directory_id: Id,
-        parent_id: Option<Id>,
-    ) -> Result<DirectoryResponse, String> {
+        change: MetadataChange,
+    ) -> Result<DirectoryResponse, FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
-            let directories = store.directories.clone();
+            let is_admin = Self::is_admin(&caller(), &store);
             let directory = store.directories.get_mut(&directory_id);
-            match Self::check_directory_state(directory) {
+            match Self::check_directory_state(directory, directory_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_directory) => {
-                    if let Some(_parent_id) = parent_id {
-                        if let Some(_parent_directory) = directories.get(&_parent_id) {
-                            if _parent_directory.owner != Some(caller()) {
-                                return Err("Parent directory is not owned by you".to_string());
-                            }
-                        } else {
-                            return Err("Parent directory does not exist".to_string());
-                        }
-                    }
-
-                    _directory.parent_id = parent_id;
+                    _directory.metadata = apply_metadata_change(&_directory.metadata, change)?;
                     _directory.updated_at = time();
                     Ok(Self::map_directory_entity_to_directory_response(
                         _directory.clone(),
@@ -145,15 +197,18 @@ impl Store {
         })
     }
 
-    // TODO: recursive method // change owner to whitelist? 🤔
-    pub fn change_directory_owner(direcory_id: Id, owner: Principal) -> Result<(), String> {
+    pub fn change_directory_header_rules(
+        directory_id: Id,
+        header_rules: Vec<HeaderRule>,
+    ) -> Result<(), FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
-            let directory = store.directories.get_mut(&direcory_id);
-            match Self::check_directory_state(directory) {
+            let is_admin = Self::is_admin(&caller(), &store);
+            let directory = store.directories.get_mut(&directory_id);
+            match Self::check_directory_state(directory, directory_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_directory) => {
-                    _directory.owner = Some(owner);
+                    _directory.header_rules = header_rules;
                     _directory.updated_at = time();
                     Ok(())
                 }
@@ -161,48 +216,195 @@ impl Store {
         })
     }
 
-    pub fn find_directory(parent_id: Option<u64>, child_name: String) -> Option<DirectoryEntity> {
+    pub fn change_directory_parent(
+        directory_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<DirectoryResponse, FileManagerError> {
         STORE.with(|store| {
-            let store = store.borrow();
-            store
-                .directories
-                .values()
-                .find(|dir| {
-                    parent_id == dir.parent_id && child_name == dir.name.replace(" ", "%20")
-                })
-                .cloned()
+            let mut store = store.borrow_mut();
+            Self::check_parent_directory(parent_id, &store)?;
+            let is_admin = Self::is_admin(&caller(), &store);
+            let directory = store.directories.get_mut(&directory_id);
+            let previous = Self::check_directory_state(directory, directory_id, is_admin)?.clone();
+
+            if let Some(_parent_id) = parent_id {
+                if Self::is_inside_directory(parent_id, directory_id, &store) {
+                    return Err(FileManagerError::InvalidParent {
+                        directory_id,
+                        parent_id: _parent_id,
+                    });
+                }
+            }
+
+            validate_name(&previous.name, Self::is_document_root(parent_id, &store))?;
+            Self::check_sibling_name(
+                &previous.name,
+                parent_id,
+                Some(&AssetWithId::Directory(directory_id)),
+                &store,
+            )?;
+
+            let directory = DirectoryEntity {
+                parent_id,
+                updated_at: time(),
+                ..previous.clone()
+            };
+            store.directories.insert(directory_id, directory.clone());
+
+            Self::reindex_directory(&previous, &directory, &mut store);
+            Ok(Self::map_directory_entity_to_directory_response(
+                directory, &store,
+            ))
         })
     }
 
-    pub fn create_directory(
-        name: String,
-        permission: Permission,
+    pub fn copy_directory(
+        directory_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<DirectoryResponse, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            Self::check_parent_directory(parent_id, &store)?;
+
+            match store.directories.get(&directory_id).cloned() {
+                None => Err(FileManagerError::DirectoryNotFound(directory_id)),
+                Some(_directory) => {
+                    if !Self::is_readable(
+                        _directory.owner,
+                        &_directory.permission,
+                        caller(),
+                        &store,
+                    ) {
+                        return Err(FileManagerError::NotReadable(AssetWithId::Directory(
+                            directory_id,
+                        )));
+                    }
+
+                    if let Some(_parent_id) = parent_id {
+                        if Self::is_inside_directory(parent_id, directory_id, &store) {
+                            return Err(FileManagerError::InvalidParent {
+                                directory_id,
+                                parent_id: _parent_id,
+                            });
+                        }
+                    }
+
+                    Self::check_sibling_name(&_directory.name, parent_id, None, &store)?;
+
+                    let copy = Self::_copy_directory(&_directory, parent_id, &mut store);
+                    Ok(Self::map_directory_entity_to_directory_response(
+                        copy, &store,
+                    ))
+                }
+            }
+        })
+    }
+
+    // Copy the directory and its readable children, the caller becomes the owner of the copies
+    fn _copy_directory(
+        directory: &DirectoryEntity,
         parent_id: Option<Id>,
-    ) -> Result<DirectoryEntity, String> {
-        let invalid_chars = ["/", "*", "\\", ":", "?", "\"", "<", ">", "'"];
+        store: &mut Store,
+    ) -> DirectoryEntity {
+        let directory_id = store.directory_id;
+        let copy = DirectoryEntity {
+            id: directory_id,
+            parent_id,
+            is_protected: false,
+            owner: Some(caller()),
+            created_at: time(),
+            updated_at: time(),
+            ..directory.clone()
+        };
+        store.directories.insert(directory_id, copy.clone());
+        Self::index_directory(&copy, store);
+        store.directory_id += 1;
 
-        if invalid_chars.iter().any(|&c| name.contains(c)) {
-            return Err("Invalid directory name".to_string());
+        let child_directories: Vec<DirectoryEntity> =
+            Self::get_child_directories(Some(directory.id), store)
+                .into_iter()
+                .filter(|d| Self::is_readable(d.owner, &d.permission, caller(), store))
+                .cloned()
+                .collect();
+
+        let child_files: Vec<FileEntity> = Self::get_child_files(Some(directory.id), store)
+            .into_iter()
+            .filter(|f| Self::is_readable(f.owner, &f.permission, caller(), store))
+            .cloned()
+            .collect();
+
+        for child in child_files {
+            Self::_copy_file(&child, Some(directory_id), store);
         }
 
-        STORE.with(|store| {
-            let mut store = store.borrow_mut();
+        for child in child_directories {
+            Self::_copy_directory(&child, Some(directory_id), store);
+        }
 
-            // check if the files / directories are protected or owned by the caller
-            if let Some(_parent_id) = parent_id {
-                if let Some(_directory) = store.directories.get(&_parent_id) {
+        copy
+    }
+
+    // Walk up from the parent to check if it is the directory itself or one of its descendants
+    pub fn is_inside_directory(parent_id: Option<Id>, directory_id: Id, store: &Store) -> bool {
+        let mut ancestor_id = parent_id;
+        // The depth is limited by the number of directories, in case the tree already contains a cycle
+        for _ in 0..=store.directories.len() {
+            match ancestor_id {
+                None => return false,
+                Some(_ancestor_id) if _ancestor_id == directory_id => return true,
+                Some(_ancestor_id) => {
+                    ancestor_id = store
+                        .directories
+                        .get(&_ancestor_id)
+                        .and_then(|d| d.parent_id);
+                }
+            }
+        }
+        true
+    }
+
+    // Check if assets can be added to the parent directory by the caller
+    // Only writers can add assets to the root, admins can add assets to directories of others
+    pub fn check_parent_directory(
+        parent_id: Option<Id>,
+        store: &Store,
+    ) -> Result<(), FileManagerError> {
+        match parent_id {
+            None => {
+                if !Self::has_role(&caller(), Role::Writer, store) {
+                    return Err(FileManagerError::NotWriter);
+                }
+            }
+            Some(_parent_id) => match store.directories.get(&_parent_id) {
+                None => return Err(FileManagerError::DirectoryNotFound(_parent_id)),
+                Some(_directory) => {
                     if _directory.is_protected {
-                        return Err(format!("Parent directory {} is protected", _parent_id));
+                        return Err(FileManagerError::Protected(AssetWithId::Directory(
+                            _parent_id,
+                        )));
                     }
 
-                    if _directory.owner != Some(caller()) {
-                        return Err(format!(
-                            "Parent directory {} is not owned by you",
-                            _parent_id
-                        ));
+                    if _directory.owner != Some(caller()) && !Self::is_admin(&caller(), store) {
+                        return Err(FileManagerError::NotOwner(AssetWithId::Directory(
+                            _parent_id,
+                        )));
                     }
                 }
-            }
+            },
+        }
+        Ok(())
+    }
+
+    pub fn create_directory(
+        name: String,
+        permission: Permission,
+        parent_id: Option<Id>,
+    ) -> Result<DirectoryEntity, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            Self::check_parent_directory(parent_id, &store)?;
+            let name = validate_name(&name, Self::is_document_root(parent_id, &store))?;
+            Self::check_sibling_name(&name, parent_id, None, &store)?;
 
             let directory_id = store.directory_id;
             let directory = DirectoryEntity {
@@ -210,49 +412,129 @@ impl Store {
                 name,
                 parent_id,
                 permission,
+                headers: vec![],
+                header_rules: vec![],
+                metadata: Default::default(),
                 created_at: time(),
                 updated_at: time(),
                 is_protected: false,
                 owner: Some(caller()),
             };
             store.directories.insert(directory_id, directory.clone());
+            Self::index_directory(&directory, &mut store);
             store.directory_id += 1;
             Ok(directory)
         })
     }
 
+    // Get the storage used by each file and directory in the directory (root if None), largest first
+    pub fn get_storage_breakdown(
+        directory_id: Option<Id>,
+    ) -> Result<Vec<StorageUsage>, FileManagerError> {
+        STORE.with(|store| {
+            let store = store.borrow();
+            if let Some(_directory_id) = directory_id {
+                if !store.directories.contains_key(&_directory_id) {
+                    return Err(FileManagerError::DirectoryNotFound(_directory_id));
+                }
+            }
+
+            let mut usages: Vec<StorageUsage> = vec![];
+            for _directory in Self::get_child_directories(directory_id, &store) {
+                usages.push(StorageUsage {
+                    asset: AssetWithId::Directory(_directory.id),
+                    name: _directory.name.clone(),
+                    stats: store
+                        .directory_stats
+                        .get(&_directory.id)
+                        .cloned()
+                        .unwrap_or_default(),
+                });
+            }
+
+            for _file in Self::get_child_files(directory_id, &store) {
+                usages.push(StorageUsage {
+                    asset: AssetWithId::File(_file.id),
+                    name: _file.name.clone(),
+                    stats: DirectoryStats {
+                        size: _file.size,
+                        file_count: 1,
+                        directory_count: 0,
+                    },
+                });
+            }
+
+            usages.sort_by_key(|usage| std::cmp::Reverse(usage.stats.size));
+            Ok(usages)
+        })
+    }
+
+    // Map the directory without its children, only the number of children is included
     pub fn map_directory_entity_to_directory_response(
         directory: DirectoryEntity,
         store: &Store,
     ) -> DirectoryResponse {
+        Self::map_directory_entity(directory, vec![], store)
+    }
+
+    pub fn map_directory_entity(
+        directory: DirectoryEntity,
+        children: Vec<Asset>,
+        store: &Store,
+    ) -> DirectoryResponse {
+        let (file_count, directory_count) = match store.children.get(&Some(directory.id)) {
+            Some(_children) => (
+                _children.files.len() as u64,
+                _children.directories.len() as u64,
+            ),
+            None => (0, 0),
+        };
+
         DirectoryResponse {
             id: directory.id,
             name: directory.name,
             permission: directory.permission,
+            headers: directory.headers,
+            header_rules: directory.header_rules,
+            metadata: directory.metadata,
             parent_id: directory.parent_id,
             created_at: directory.created_at,
             updated_at: directory.updated_at,
-            children: Self::get_directory_child_assets(directory.id, store),
+            children,
+            file_count,
+            directory_count,
+            stats: store
+                .directory_stats
+                .get(&directory.id)
+                .cloned()
+                .unwrap_or_default(),
             is_protected: directory.is_protected,
             owner: directory.owner,
         }
     }
 
+    // Admins can change directories of others, protected directories can not be changed by anyone
     fn check_directory_state(
         directory: Option<&mut DirectoryEntity>,
-    ) -> Result<&mut DirectoryEntity, String> {
+        directory_id: Id,
+        is_admin: bool,
+    ) -> Result<&mut DirectoryEntity, FileManagerError> {
         if let Some(_directory) = directory {
             if _directory.is_protected {
-                return Err("Directory is protected".to_string());
+                return Err(FileManagerError::Protected(AssetWithId::Directory(
+                    directory_id,
+                )));
             }
 
-            if _directory.owner != Some(caller()) {
-                return Err("Directory is not owned by you".to_string());
+            if _directory.owner != Some(caller()) && !is_admin {
+                return Err(FileManagerError::NotOwner(AssetWithId::Directory(
+                    directory_id,
+                )));
             }
 
-            return Ok(_directory);
+            Ok(_directory)
         } else {
-            return Err("Directory not found".to_string());
+            Err(FileManagerError::DirectoryNotFound(directory_id))
         }
     }
 }
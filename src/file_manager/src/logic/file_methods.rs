@@ -1,60 +1,103 @@
-use candid::Principal;
 use ic_cdk::{api::time, caller};
 
 use crate::{
+    helpers::{
+        metadata_helper::apply_metadata_change,
+        mime_helper::{
+            get_extension, matches_signature, resolve_mime_type, sniff_mime_type, DEFAULT_MIME_TYPE,
+        },
+        name_helper::validate_name,
+    },
     models::{
-        asset_models::{Id, Permission},
-        file_models::{FileEntity, FileResponse},
+        asset_models::{AssetWithId, Id, Permission},
+        error_models::FileManagerError,
+        file_models::{ContentDisposition, FileEntity, FileResponse},
+        http_models::HeaderField,
+        metadata_models::MetadataChange,
     },
     store::{Store, STORE},
 };
 
+// Maximum size of a range read with `get_file_range`, below the size limit of a response
+const MAX_RANGE_SIZE: u64 = 2_000_000;
+
 impl Store {
-    pub fn delete_file(file_id: Id) -> Result<(), String> {
+    pub fn delete_file(file_id: Id) -> Result<(), FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
             Self::_delete_file(file_id, &mut store)
         })
     }
 
-    pub fn _delete_file(file_id: Id, store: &mut Store) -> Result<(), String> {
+    pub fn _delete_file(file_id: Id, store: &mut Store) -> Result<(), FileManagerError> {
+        let is_admin = Self::is_admin(&caller(), store);
         let file = store.files.get_mut(&file_id);
-        match Self::check_file_state(file) {
+        match Self::check_file_state(file, file_id, is_admin) {
             Err(err) => Err(err),
             Ok(_file) => {
-                let chunk_ids = _file.chunks.clone();
-                for chunk_id in chunk_ids {
-                    store.chunks.remove(&chunk_id);
+                let file = _file.clone();
+                for chunk_id in &file.chunks {
+                    store.chunks.remove(chunk_id);
                 }
+                Self::remove_image_variants(file_id, store);
+                Self::unindex_file(&file, store);
+                Self::unindex_file_text(file_id, store);
+                store.extractions.remove(&file_id);
+                store.transfers.remove(&AssetWithId::File(file_id));
                 store.files.remove(&file_id);
                 Ok(())
             }
         }
     }
 
-    pub fn change_file_name(file_id: Id, name: String) -> Result<FileResponse, String> {
-        let invalid_chars = ["/", "*", "\\", ":", "?", "\"", "<", ">", "'"];
-
-        if invalid_chars.iter().any(|&c| name.contains(c)) {
-            return Err("Invalid directory name".to_string());
-        }
-
+    pub fn change_file_name(file_id: Id, name: String) -> Result<FileResponse, FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
-            let files = store.files.clone();
+            let is_admin = Self::is_admin(&caller(), &store);
+            let file = store.files.get_mut(&file_id);
+            let previous = Self::check_file_state(file, file_id, is_admin)?.clone();
 
-            if files
-                .iter()
-                .any(|(_id, _file)| _file.parent_id == Some(file_id) && _file.name == name)
-            {
-                return Err("File with same name already exists".to_string());
+            // Names are checked after the permission, the names of other assets can not be probed
+            let name = validate_name(&name, Self::is_document_root(previous.parent_id, &store))?;
+            Self::check_sibling_name(
+                &name,
+                previous.parent_id,
+                Some(&AssetWithId::File(file_id)),
+                &store,
+            )?;
+
+            // The mime type follows the new extension
+            let extension = get_extension(&name).to_string();
+            let file = FileEntity {
+                mime_type: resolve_mime_type(&extension, &previous.mime_type),
+                extension,
+                name,
+                updated_at: time(),
+                ..previous.clone()
+            };
+            store.files.insert(file_id, file.clone());
+
+            Self::reindex_file(&previous, &file, &mut store);
+            // The file may be (no longer) a text file that is searchable
+            if previous.mime_type != file.mime_type {
+                Self::index_file_text(file_id, &mut store);
             }
+            Ok(Self::map_file_entity_to_file_response(file, &store))
+        })
+    }
 
+    pub fn change_file_permission(
+        file_id: Id,
+        permission: Permission,
+    ) -> Result<FileResponse, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
             let file = store.files.get_mut(&file_id);
-            match Self::check_file_state(file) {
+            match Self::check_file_state(file, file_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_file) => {
-                    _file.name = name;
+                    _file.permission = permission;
                     _file.updated_at = time();
                     Ok(Self::map_file_entity_to_file_response(
                         _file.clone(),
@@ -65,17 +108,18 @@ impl Store {
         })
     }
 
-    pub fn change_file_permission(
+    pub fn change_file_headers(
         file_id: Id,
-        permission: Permission,
-    ) -> Result<FileResponse, String> {
+        headers: Vec<HeaderField>,
+    ) -> Result<FileResponse, FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
             let file = store.files.get_mut(&file_id);
-            match Self::check_file_state(file) {
+            match Self::check_file_state(file, file_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_file) => {
-                    _file.permission = permission;
+                    _file.headers = headers;
                     _file.updated_at = time();
                     Ok(Self::map_file_entity_to_file_response(
                         _file.clone(),
@@ -86,40 +130,40 @@ impl Store {
         })
     }
 
-    pub fn change_file_owner(file_id: Id, owner: Principal) -> Result<(), String> {
+    pub fn change_file_metadata(
+        file_id: Id,
+        change: MetadataChange,
+    ) -> Result<FileResponse, FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
             let file = store.files.get_mut(&file_id);
-            match Self::check_file_state(file) {
+            match Self::check_file_state(file, file_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_file) => {
-                    _file.owner = Some(owner);
+                    _file.metadata = apply_metadata_change(&_file.metadata, change)?;
                     _file.updated_at = time();
-                    Ok(())
+                    Ok(Self::map_file_entity_to_file_response(
+                        _file.clone(),
+                        &store,
+                    ))
                 }
             }
         })
     }
 
-    pub fn change_file_parent(file_id: Id, parent_id: Option<Id>) -> Result<FileResponse, String> {
+    pub fn change_file_disposition(
+        file_id: Id,
+        disposition: ContentDisposition,
+    ) -> Result<FileResponse, FileManagerError> {
         STORE.with(|store| {
             let mut store = store.borrow_mut();
-            let directories = store.directories.clone();
+            let is_admin = Self::is_admin(&caller(), &store);
             let file = store.files.get_mut(&file_id);
-            match Self::check_file_state(file) {
+            match Self::check_file_state(file, file_id, is_admin) {
                 Err(err) => Err(err),
                 Ok(_file) => {
-                    if let Some(_parent_id) = parent_id {
-                        if let Some(_parent_directory) = directories.get(&_parent_id) {
-                            if _parent_directory.owner != Some(caller()) {
-                                return Err("Parent directory is not owned by you".to_string());
-                            }
-                        } else {
-                            return Err("Parent directory does not exist".to_string());
-                        }
-                    }
-
-                    _file.parent_id = parent_id;
+                    _file.disposition = disposition;
                     _file.updated_at = time();
                     Ok(Self::map_file_entity_to_file_response(
                         _file.clone(),
@@ -130,29 +174,259 @@ impl Store {
         })
     }
 
-    pub fn find_file(parent_id: Option<u64>, path_section: String) -> Option<FileEntity> {
+    pub fn change_file_parent(
+        file_id: Id,
+        parent_id: Option<Id>,
+    ) -> Result<FileResponse, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            Self::check_parent_directory(parent_id, &store)?;
+            let is_admin = Self::is_admin(&caller(), &store);
+            let file = store.files.get_mut(&file_id);
+            let previous = Self::check_file_state(file, file_id, is_admin)?.clone();
+
+            validate_name(&previous.name, Self::is_document_root(parent_id, &store))?;
+            Self::check_sibling_name(
+                &previous.name,
+                parent_id,
+                Some(&AssetWithId::File(file_id)),
+                &store,
+            )?;
+
+            let file = FileEntity {
+                parent_id,
+                updated_at: time(),
+                ..previous.clone()
+            };
+            store.files.insert(file_id, file.clone());
+
+            Self::reindex_file(&previous, &file, &mut store);
+            Ok(Self::map_file_entity_to_file_response(file, &store))
+        })
+    }
+
+    pub fn copy_file(file_id: Id, parent_id: Option<Id>) -> Result<FileResponse, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            Self::check_parent_directory(parent_id, &store)?;
+
+            match store.files.get(&file_id).cloned() {
+                None => Err(FileManagerError::FileNotFound(file_id)),
+                Some(_file) => {
+                    if !Self::is_readable(_file.owner, &_file.permission, caller(), &store) {
+                        return Err(FileManagerError::NotReadable(AssetWithId::File(file_id)));
+                    }
+
+                    Self::check_sibling_name(&_file.name, parent_id, None, &store)?;
+
+                    let copy = Self::_copy_file(&_file, parent_id, &mut store);
+                    Ok(Self::map_file_entity_to_file_response(copy, &store))
+                }
+            }
+        })
+    }
+
+    // Copy the file and its chunks to the parent, the caller becomes the owner of the copy
+    pub fn _copy_file(file: &FileEntity, parent_id: Option<Id>, store: &mut Store) -> FileEntity {
+        let file_id = store.file_id;
+        let mut copy = FileEntity {
+            id: file_id,
+            parent_id,
+            chunks: Self::copy_chunks(&file.chunks, store),
+            version: 0,
+            is_protected: false,
+            owner: Some(caller()),
+            created_at: time(),
+            updated_at: time(),
+            ..file.clone()
+        };
+
+        for variant in copy.variants.iter_mut() {
+            variant.chunks = Self::copy_chunks(&variant.chunks, store);
+        }
+
+        store.files.insert(file_id, copy.clone());
+        Self::index_file(&copy, store);
+        Self::index_file_text(file_id, store);
+        store.file_id += 1;
+        copy
+    }
+
+    // Copy the chunks to new chunk ids, empty files have no chunks to copy
+    fn copy_chunks(chunk_ids: &[Id], store: &mut Store) -> Vec<Id> {
+        let mut copy_chunk_ids: Vec<Id> = vec![];
+        for chunk_id in chunk_ids {
+            let bytes = store.chunks.get(chunk_id).cloned().unwrap_or_default();
+            let copy_chunk_id = store.chunk_id;
+            store.chunks.insert(copy_chunk_id, bytes);
+            copy_chunk_ids.push(copy_chunk_id);
+            store.chunk_id += 1;
+        }
+        copy_chunk_ids
+    }
+
+    pub fn add_chunks(chunks: Vec<(Id, Vec<u8>)>) -> Result<(), FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            let is_admin = Self::is_admin(&caller(), &store);
+
+            // Only let the owner of the file upload the corresponding chunk,
+            // check every chunk before anything is stored
+            let mut chunk_file_ids: Vec<Id> = vec![];
+            for (chunk_id, _) in &chunks {
+                let file_id = match store.chunk_files.get(chunk_id) {
+                    Some(_file_id) => *_file_id,
+                    None => {
+                        return Err(FileManagerError::InvalidArgument(format!(
+                            "Chunk {} is not part of a file",
+                            chunk_id
+                        )))
+                    }
+                };
+                Self::check_file_state(store.files.get_mut(&file_id), file_id, is_admin)?;
+                chunk_file_ids.push(file_id);
+            }
+
+            let mut updated_file_ids: Vec<Id> = vec![];
+            for ((chunk_id, bytes), file_id) in chunks.into_iter().zip(chunk_file_ids) {
+                // Running downloads of the previous content are stopped by the version change
+                if let Some(_file) = store.files.get_mut(&file_id) {
+                    _file.version += 1;
+                }
+                store.chunks.insert(chunk_id, bytes);
+
+                // Variants and the search index of the previous content are outdated, new variants
+                // are generated with a separate `generate_image_variants` call once the upload is complete
+                Self::remove_image_variants(file_id, &mut store);
+                Self::unindex_file_text(file_id, &mut store);
+                if !updated_file_ids.contains(&file_id) {
+                    updated_file_ids.push(file_id);
+                }
+            }
+
+            for file_id in updated_file_ids {
+                if let Some(_file) = store.files.get(&file_id) {
+                    if Self::is_upload_complete(_file, &store) {
+                        Self::commit_file(file_id, &mut store);
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    // Called when all chunks of a file are uploaded
+    pub fn commit_file(file_id: Id, store: &mut Store) {
+        // Correct the mime type if the content does not match its signature, ex: html labeled as png
+        let first_chunk = store
+            .files
+            .get(&file_id)
+            .and_then(|_file| _file.chunks.first())
+            .and_then(|chunk_id| store.chunks.get(chunk_id))
+            .cloned()
+            .unwrap_or_default();
+
+        if let Some(_file) = store.files.get_mut(&file_id) {
+            if !matches_signature(&_file.mime_type, &first_chunk) {
+                _file.mime_type = sniff_mime_type(&first_chunk)
+                    .unwrap_or(DEFAULT_MIME_TYPE)
+                    .to_string();
+            }
+        }
+
+        Self::index_file_text(file_id, store);
+    }
+
+    // All chunks are uploaded when their combined size matches the size of the file
+    pub fn is_upload_complete(file: &FileEntity, store: &Store) -> bool {
+        let uploaded_size: u64 = file
+            .chunks
+            .iter()
+            .filter_map(|chunk_id| store.chunks.get(chunk_id))
+            .map(|bytes| bytes.len() as u64)
+            .sum();
+        uploaded_size == file.size
+    }
+
+    // Read a chunk of a file, only if the file is readable by the caller
+    pub fn get_chunk(file_id: Id, index: u64) -> Result<Vec<u8>, FileManagerError> {
         STORE.with(|store| {
             let store = store.borrow();
-            store
-                .files
-                .values()
-                .find(|file| {
-                    parent_id == file.parent_id && path_section == file.name.replace(" ", "%20")
-                })
+            let file = Self::get_readable_file(file_id, &store)?;
+            file.chunks
+                .get(index as usize)
+                .and_then(|chunk_id| store.chunks.get(chunk_id))
                 .cloned()
+                .ok_or(FileManagerError::ChunkNotFound { file_id, index })
         })
     }
 
-    pub fn add_chunks(chunks: Vec<(Id, Vec<u8>)>) {
+    // Read a byte range of a file, only if the file is readable by the caller
+    // The range ends at the end of the uploaded content
+    pub fn get_file_range(
+        file_id: Id,
+        offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>, FileManagerError> {
+        if length > MAX_RANGE_SIZE {
+            return Err(FileManagerError::InvalidArgument(format!(
+                "Range of {} bytes is larger than the maximum of {} bytes",
+                length, MAX_RANGE_SIZE
+            )));
+        }
+
         STORE.with(|store| {
-            let mut store = store.borrow_mut();
-            for (chunk_id, bytes) in chunks {
-                // Only let the owner of the file upload the corresponding chunk
-                if let Some(_file) = store.files.iter().find(|f| f.1.chunks.contains(&chunk_id)) {
-                    store.chunks.insert(chunk_id, bytes);
+            let store = store.borrow();
+            let file = Self::get_readable_file(file_id, &store)?;
+            if offset > file.size {
+                return Err(FileManagerError::InvalidArgument(
+                    "Offset is beyond the end of the file".to_string(),
+                ));
+            }
+
+            let end = offset + length;
+            let mut bytes: Vec<u8> = vec![];
+            let mut chunk_start: u64 = 0;
+            for chunk in file
+                .chunks
+                .iter()
+                .filter_map(|chunk_id| store.chunks.get(chunk_id))
+            {
+                let chunk_end = chunk_start + chunk.len() as u64;
+                if chunk_end > offset && chunk_start < end {
+                    let from = offset.saturating_sub(chunk_start) as usize;
+                    let to = (end.min(chunk_end) - chunk_start) as usize;
+                    bytes.extend_from_slice(&chunk[from..to]);
                 }
+                if chunk_end >= end {
+                    break;
+                }
+                chunk_start = chunk_end;
             }
-        });
+            Ok(bytes)
+        })
+    }
+
+    fn get_readable_file(file_id: Id, store: &Store) -> Result<&FileEntity, FileManagerError> {
+        match store.files.get(&file_id) {
+            None => Err(FileManagerError::FileNotFound(file_id)),
+            Some(_file) => match Self::is_readable(_file.owner, &_file.permission, caller(), store)
+            {
+                true => Ok(_file),
+                false => Err(FileManagerError::NotReadable(AssetWithId::File(file_id))),
+            },
+        }
+    }
+
+    // Combine the chunks of a file into a single byte vector
+    pub fn get_file_content(file: &FileEntity, store: &Store) -> Vec<u8> {
+        file.chunks
+            .iter()
+            .filter_map(|chunk_id| store.chunks.get(chunk_id))
+            .flatten()
+            .cloned()
+            .collect()
     }
 
     pub fn map_file_entity_to_file_response(file: FileEntity, store: &Store) -> FileResponse {
@@ -166,8 +440,12 @@ impl Store {
             permission: _file.permission,
             parent_id: _file.parent_id,
             chunks: _file.chunks,
-            path: Self::get_file_path(&file, &store),
+            version: _file.version,
+            variants: _file.variants,
+            path: Self::get_file_path(&file, store),
             metadata: _file.metadata,
+            headers: _file.headers,
+            disposition: _file.disposition,
             created_at: _file.created_at,
             updated_at: _file.updated_at,
             is_protected: _file.is_protected,
@@ -175,19 +453,24 @@ impl Store {
         }
     }
 
-    fn check_file_state(file: Option<&mut FileEntity>) -> Result<&mut FileEntity, String> {
+    // Admins can change files of others, protected files can not be changed by anyone
+    pub fn check_file_state(
+        file: Option<&mut FileEntity>,
+        file_id: Id,
+        is_admin: bool,
+    ) -> Result<&mut FileEntity, FileManagerError> {
         if let Some(_file) = file {
             if _file.is_protected {
-                return Err("File is protected".to_string());
+                return Err(FileManagerError::Protected(AssetWithId::File(file_id)));
             }
 
-            if _file.owner != Some(caller()) {
-                return Err("File is not owned by you".to_string());
+            if _file.owner != Some(caller()) && !is_admin {
+                return Err(FileManagerError::NotOwner(AssetWithId::File(file_id)));
             }
 
-            return Ok(_file);
+            Ok(_file)
         } else {
-            return Err("File not found".to_string());
+            Err(FileManagerError::FileNotFound(file_id))
         }
     }
 }
@@ -0,0 +1,98 @@
+use ic_cdk::caller;
+
+use crate::{
+    helpers::header_helper::{match_header_pattern, merge_headers, parse_headers_file},
+    models::{
+        asset_models::{AssetWithId, Id},
+        directory_models::DirectoryEntity,
+        error_models::FileManagerError,
+        file_models::FileEntity,
+        http_models::{HeaderField, HeaderRule},
+    },
+    store::{Store, STORE},
+};
+
+pub const HEADERS_FILE_NAME: &str = "_headers";
+
+impl Store {
+    // Parse the `_headers` file in the directory (root if None) and store the rules on the directory
+    pub fn load_headers_file(
+        directory_id: Option<Id>,
+    ) -> Result<Vec<HeaderRule>, FileManagerError> {
+        let rules = STORE.with(|store| {
+            let store = store.borrow();
+            // Rules from the root apply to every file in the canister
+            if directory_id.is_none() && !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+            Self::read_headers_file(directory_id, &store)
+        })?;
+
+        match directory_id {
+            Some(_directory_id) => {
+                Self::change_directory_header_rules(_directory_id, rules.clone())?;
+            }
+            None => STORE.with(|store| store.borrow_mut().header_rules = rules.clone()),
+        }
+
+        Ok(rules)
+    }
+
+    fn read_headers_file(
+        directory_id: Option<Id>,
+        store: &Store,
+    ) -> Result<Vec<HeaderRule>, FileManagerError> {
+        match Self::get_child_file(directory_id, HEADERS_FILE_NAME, store) {
+            None => Err(FileManagerError::PathNotFound(
+                HEADERS_FILE_NAME.to_string(),
+            )),
+            Some(_file) => {
+                if _file.owner != Some(caller()) && !Self::is_admin(&caller(), store) {
+                    return Err(FileManagerError::NotOwner(AssetWithId::File(_file.id)));
+                }
+
+                let content = Self::get_file_content(_file, store);
+                Ok(parse_headers_file(&String::from_utf8_lossy(&content)))
+            }
+        }
+    }
+
+    // Collect the custom headers for a file, inherited from the root down to the file itself
+    // Order: root rules -> (directory headers -> directory rules) for each directory -> file headers
+    pub fn get_custom_headers(file: &FileEntity, store: &Store) -> Vec<HeaderField> {
+        let mut directories: Vec<&DirectoryEntity> = vec![];
+        let mut parent_id = file.parent_id;
+        while let Some(_directory) = parent_id.and_then(|id| store.directories.get(&id)) {
+            directories.push(_directory);
+            parent_id = _directory.parent_id;
+        }
+        directories.reverse();
+
+        let mut segments: Vec<&str> = directories.iter().map(|d| d.name.as_str()).collect();
+        segments.push(file.name.as_str());
+
+        let mut headers: Vec<HeaderField> = vec![];
+        Self::apply_header_rules(&mut headers, &store.header_rules, &segments);
+
+        for (depth, directory) in directories.iter().enumerate() {
+            merge_headers(&mut headers, &directory.headers);
+            // Rules in a `_headers` file are relative to the directory the file is placed in
+            Self::apply_header_rules(
+                &mut headers,
+                &directory.header_rules,
+                &segments[depth + 1..],
+            );
+        }
+
+        merge_headers(&mut headers, &file.headers);
+        headers
+    }
+
+    pub fn apply_header_rules(headers: &mut Vec<HeaderField>, rules: &[HeaderRule], path: &[&str]) {
+        let path = format!("/{}", path.join("/"));
+        rules
+            .iter()
+            .filter(|rule| match_header_pattern(&rule.pattern, &path))
+            .for_each(|rule| merge_headers(headers, &rule.headers));
+    }
+}
@@ -0,0 +1,113 @@
+use ic_cdk::caller;
+
+use crate::{
+    helpers::name_helper::is_reserved_name,
+    models::{
+        asset_models::Id,
+        error_models::{FileManagerError, InvalidNameReason},
+        http_models::{HeaderField, HostMapping},
+    },
+    store::{Store, STORE},
+};
+
+impl Store {
+    pub fn set_host_mapping(
+        host: String,
+        directory_id: Id,
+    ) -> Result<HostMapping, FileManagerError> {
+        let host = Self::normalize_host(&host);
+        if host.is_empty() || host.contains(['/', ' ', '?', '#']) {
+            return Err(FileManagerError::InvalidHost(host));
+        }
+
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+
+            if !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+
+            if !store.directories.contains_key(&directory_id) {
+                return Err(FileManagerError::DirectoryNotFound(directory_id));
+            }
+
+            // Assets with a reserved name would be hidden once the directory is a document root
+            if let Some(_children) = store.children.get(&Some(directory_id)) {
+                if let Some(name) = _children
+                    .files
+                    .keys()
+                    .chain(_children.directories.keys())
+                    .find(|name| is_reserved_name(name))
+                {
+                    return Err(FileManagerError::InvalidName {
+                        name: name.clone(),
+                        reason: InvalidNameReason::Reserved,
+                    });
+                }
+            }
+
+            store.hosts.insert(host.clone(), directory_id);
+            Ok(HostMapping { host, directory_id })
+        })
+    }
+
+    pub fn remove_host_mapping(host: String) -> Result<(), FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+
+            if !Self::is_admin(&caller(), &store) {
+                return Err(FileManagerError::NotAdmin);
+            }
+
+            let host = Self::normalize_host(&host);
+            match store.hosts.remove(&host) {
+                Some(_) => Ok(()),
+                None => Err(FileManagerError::HostNotFound(host)),
+            }
+        })
+    }
+
+    pub fn get_host_mappings() -> Vec<HostMapping> {
+        STORE.with(|store| {
+            store
+                .borrow()
+                .hosts
+                .iter()
+                .map(|(host, directory_id)| HostMapping {
+                    host: host.clone(),
+                    directory_id: *directory_id,
+                })
+                .collect()
+        })
+    }
+
+    // Get the directory that serves as the document root for the `host` header of the request,
+    // unmapped hosts are served from the root (None)
+    pub fn get_host_root(headers: &[HeaderField], store: &Store) -> Option<Id> {
+        headers
+            .iter()
+            .find(|header| header.0.eq_ignore_ascii_case("host"))
+            .and_then(|header| store.hosts.get(&Self::normalize_host(&header.1)))
+            .filter(|directory_id| store.directories.contains_key(directory_id))
+            .cloned()
+    }
+
+    // Lowercase the host and remove the port and trailing dot; ex: `Example.com.:443` -> `example.com`
+    fn normalize_host(host: &str) -> String {
+        let host = host.trim().to_lowercase();
+        let host = match host.rsplit_once(':') {
+            // Keep ipv6 addresses (`[::1]`) intact
+            Some((_host, port)) if !port.contains(']') => _host.to_string(),
+            _ => host,
+        };
+        host.trim_end_matches('.').to_string()
+    }
+
+    // Assets in the root or in a directory mapped to a host are served at the top level of a website
+    pub fn is_document_root(directory_id: Option<Id>, store: &Store) -> bool {
+        match directory_id {
+            None => true,
+            Some(_directory_id) => store.hosts.values().any(|id| *id == _directory_id),
+        }
+    }
+}
@@ -1,12 +1,17 @@
 use ic_cdk::id;
 
 use crate::{
-    helpers::ic_data_helper,
+    helpers::{
+        header_helper::merge_headers,
+        ic_data_helper,
+        url_helper::{decode_path, get_attachment_disposition, get_query_param, split_url},
+    },
     models::{
-        file_models::FileEntity,
+        asset_models::{AssetWithId, Id},
+        file_models::{ContentDisposition, FileEntity},
         http_models::{
-            AssetEncoding, HeaderField, HttpRequest, HttpResponse, PathEntry,
-            StreamingCallbackToken, StreamingStrategy,
+            AssetEncoding, HeaderField, HttpRequest, HttpResponse, PathEntry, ServeOptions,
+            StreamingCallbackHttpResponse, StreamingCallbackToken, StreamingStrategy,
         },
         misc_models::Metadata,
     },
@@ -18,62 +23,143 @@ impl Store {
     // TODO: Serve folders over http
     pub fn http_request(req: HttpRequest) -> HttpResponse {
         // Get the path and split it by '/' so we can get the path segments; ex: ['directories', 'directory', 'file.txt']
-        let mut path: Vec<&str> = req.url.as_str().split('/').collect();
-        path = path.iter().filter(|p| !p.is_empty()).cloned().collect();
+        // the query parameters are split off; ex: `?download`
+        let (path, params) = split_url(&req.url);
+
+        let options = ServeOptions {
+            // Force the browser to download the file instead of displaying it
+            download: get_query_param(&params, "download")
+                .map(|value| value != "false" && value != "0")
+                .unwrap_or(false),
+            // Serve a resized variant of an image
+            width: get_query_param(&params, "w").and_then(|value| value.parse().ok()),
+            // Download a directory as zip archive
+            zip: get_query_param(&params, "zip").is_some(),
+        };
+
+        // Directory to serve as the document root for the requested host (None is the root)
+        let root_id = STORE.with(|store| Self::get_host_root(&req.headers, &store.borrow()));
+
+        // Headers from the `_headers` file in the document root that match the requested path
+        let mut path_headers: Vec<HeaderField> = vec![];
+        STORE.with(|store| {
+            let store = store.borrow();
+            let header_rules = match root_id.and_then(|id| store.directories.get(&id)) {
+                Some(_directory) => &_directory.header_rules,
+                None => &store.header_rules,
+            };
+            Self::apply_header_rules(&mut path_headers, header_rules, &path)
+        });
 
         // Create a permission denied response
         let not_found = HttpResponse {
             status_code: 404,
-            headers: vec![],
+            headers: path_headers.clone(),
             body: vec![],
             streaming_strategy: None,
         };
 
-        // Create a path entry to serve content by
-        let mut asset_paths = vec![PathEntry {
-            // Segments to match the path against
-            match_path: vec!["directories".to_string()],
-            response: HttpResponse {
+        if options.zip {
+            return match req.method.as_str() {
+                "GET" => {
+                    Self::get_directory_archive(&path, root_id, path_headers).unwrap_or(not_found)
+                }
+                _ => not_found,
+            };
+        }
+
+        if req.method != "GET" {
+            return not_found;
+        }
+
+        // The assets in the document root as json
+        if path == ["directories"] {
+            return HttpResponse {
                 status_code: 200,
-                headers: vec![],
-                body: serde_json::to_string(&Self::get_assets_tree(None, None))
+                headers: path_headers,
+                body: serde_json::to_string(&Self::get_assets_tree(root_id, None, None))
                     .unwrap()
                     .as_bytes()
                     .to_vec(),
                 streaming_strategy: None,
-            },
-        }];
-
-        // Implementation so serve files by the correct path
-        if let Some(mut file_path) = Self::get_file_by_path(&path) {
-            file_path.match_path = file_path
-                .match_path
-                .iter()
-                .map(|s| s.replace(" ", "%20"))
-                .collect();
-            ic_cdk::println!("Matched path: {:?}", file_path.match_path);
-            asset_paths.push(file_path);
+            };
         }
 
-        match req.method.as_str() {
-            "GET" => {
-                let response = asset_paths.iter().find(|a| a.match_path == path);
-                match response.cloned() {
-                    None => not_found,
-                    Some(_response) => _response.response,
+        match Self::get_file_by_path(&path, root_id, &options) {
+            Some(_path_entry) => _path_entry.response,
+            None => not_found,
+        }
+    }
+
+    pub fn http_request_streaming_callback(
+        token: StreamingCallbackToken,
+    ) -> StreamingCallbackHttpResponse {
+        if let Some(archive) = token.archive {
+            return Self::http_request_archive_callback(archive, &token.headers);
+        }
+
+        STORE.with(|store| {
+            let store = store.borrow();
+
+            // Only serve chunks of the same file content the stream was started with
+            let file = store
+                .files
+                .get(&token.file_id)
+                .filter(|_file| _file.version == token.version);
+
+            let chunks = file.and_then(|_file| match token.variant {
+                Some(width) => _file
+                    .variants
+                    .iter()
+                    .find(|variant| variant.width == width)
+                    .map(|variant| &variant.chunks),
+                None => Some(&_file.chunks),
+            });
+
+            let body = chunks
+                .and_then(|_chunks| _chunks.get(token.chunk_index))
+                .and_then(|chunk_id| store.chunks.get(chunk_id));
+
+            match (file, chunks, body) {
+                (Some(_file), Some(_chunks), Some(_body)) => {
+                    let encoding = AssetEncoding {
+                        content_chunks: _chunks.clone(),
+                        total_length: _file.size as u128,
+                    };
+
+                    StreamingCallbackHttpResponse {
+                        token: Self::create_token(
+                            _file,
+                            token.variant,
+                            token.chunk_index,
+                            &encoding,
+                            &token.headers,
+                        ),
+                        body: _body.clone(),
+                    }
                 }
+                // End the stream, the response will be incomplete instead of mixing content
+                _ => StreamingCallbackHttpResponse {
+                    token: None,
+                    body: vec![],
+                },
             }
-            _ => not_found,
-        }
+        })
     }
 
     pub fn streaming_strategy(
-        file_id: &u64,
+        file: &FileEntity,
+        variant: Option<u32>,
         encoding: &AssetEncoding,
         headers: &[HeaderField],
     ) -> Option<StreamingStrategy> {
+        // Files with a single chunk (or none) are sent in the first response
+        if encoding.content_chunks.len() <= 1 {
+            return None;
+        }
+
         let streaming_token: Option<StreamingCallbackToken> =
-            Self::create_token(file_id, 0, encoding, headers);
+            Self::create_token(file, variant, 0, encoding, headers);
 
         streaming_token.map(|streaming_token| StreamingStrategy::Callback {
             callback: candid::Func {
@@ -85,7 +171,8 @@ impl Store {
     }
 
     pub fn create_token(
-        file_id: &u64,
+        file: &FileEntity,
+        variant: Option<u32>,
         chunk_index: usize,
         encoding: &AssetEncoding,
         headers: &[HeaderField],
@@ -94,92 +181,129 @@ impl Store {
             return None;
         }
         Some(StreamingCallbackToken {
-            file_id: file_id.clone(),
+            file_id: file.id,
+            version: file.version,
+            variant,
             headers: headers.to_owned(),
             chunk_index: chunk_index + 1,
+            archive: None,
         })
     }
 
-    // This can probably change to file.path (not implemented for directories yet)
-    pub fn get_file_by_path(path: &Vec<&str>) -> Option<PathEntry> {
-        let mut file: Option<FileEntity> = None;
-
-        if path.len() == 0 {
-            file = Self::find_file(None, "index.html".to_string());
-        } else {
-            match Self::find_directory(None, path[0].to_string()) {
-                Some(_directory) => {
-                    let mut directory = _directory;
-                    for section in path.iter().skip(1) {
-                        match Self::find_directory(Some(directory.id), section.to_string()) {
-                            Some(_directory) => {
-                                directory = _directory;
-                            }
-                            None => {
-                                file =
-                                    match Self::find_file(Some(directory.id), section.to_string()) {
-                                        Some(_file) => Some(_file),
-                                        None => Self::find_file(
-                                            Some(directory.id),
-                                            "index.html".to_string(),
-                                        ),
-                                    }
-                            }
-                        };
-                    }
-                }
-                None => {
-                    file = match Self::find_file(None, path[0].to_string()) {
-                        Some(_file) => Some(_file),
-                        None => Self::find_file(None, "index.html".to_string()),
-                    }
-                }
-            }
-        }
+    pub fn get_file_by_path(
+        path: &Vec<&str>,
+        root_id: Option<Id>,
+        options: &ServeOptions,
+    ) -> Option<PathEntry> {
+        let file = STORE.with(|store| Self::resolve_file(path, root_id, &store.borrow()).cloned());
 
         match file {
             Some(_file) => STORE.with(|store| {
                 let store = store.borrow();
 
-                let headers = vec![
-                    HeaderField("content-type".to_string(), _file.mime_type.to_string()),
+                // Serve a resized variant instead of the original image if requested
+                let variant = options
+                    .width
+                    .and_then(|width| Self::get_image_variant(&_file, width));
+
+                let (mime_type, size, chunks) = match variant {
+                    Some(_variant) => (&_variant.mime_type, _variant.size, &_variant.chunks),
+                    None => (&_file.mime_type, _file.size, &_file.chunks),
+                };
+
+                let mut headers = vec![
+                    HeaderField("content-type".to_string(), mime_type.to_string()),
                     HeaderField("accept-ranges".to_string(), "bytes".to_string()),
-                    HeaderField("content-length".to_string(), _file.size.to_string()),
+                    HeaderField("content-length".to_string(), size.to_string()),
+                    // Browsers should not guess the type of the content
+                    HeaderField("x-content-type-options".to_string(), "nosniff".to_string()),
                     // HeaderField(
                     //     "access-control-allow-origin".to_string(),
                     //     format!("https://{}.raw.ic0.app", id().to_string()),
                     // ),
                 ];
+                merge_headers(&mut headers, &Self::get_custom_headers(&_file, &store));
+
+                if options.download || matches!(_file.disposition, ContentDisposition::Attachment) {
+                    merge_headers(
+                        &mut headers,
+                        &[HeaderField(
+                            "content-disposition".to_string(),
+                            get_attachment_disposition(&_file.name),
+                        )],
+                    );
+                }
 
                 let encoding = AssetEncoding {
-                    content_chunks: _file.chunks.clone(),
-                    total_length: _file.size as u128,
+                    content_chunks: chunks.clone(),
+                    total_length: size as u128,
                 };
 
-                let body = store.chunks.get(&_file.chunks[0]).unwrap().clone();
+                let match_path = path.iter().map(|p| p.to_string()).collect();
 
-                Some(PathEntry {
-                    match_path: path.iter().map(|p| p.to_string()).collect(),
-                    response: HttpResponse {
-                        status_code: 200,
-                        headers: headers.clone(),
-                        body,
-                        streaming_strategy: Self::streaming_strategy(
-                            &_file.id, &encoding, &headers,
-                        ),
-                    },
-                })
+                // Empty files have no chunks and are served with an empty body
+                let body: Option<Vec<u8>> = match chunks.first() {
+                    Some(chunk_id) => store.chunks.get(chunk_id).cloned(),
+                    None => Some(vec![]),
+                };
+
+                match body {
+                    Some(body) => Some(PathEntry {
+                        match_path,
+                        response: HttpResponse {
+                            status_code: 200,
+                            headers: headers.clone(),
+                            body,
+                            streaming_strategy: Self::streaming_strategy(
+                                &_file,
+                                variant.map(|_variant| _variant.width),
+                                &encoding,
+                                &headers,
+                            ),
+                        },
+                    }),
+                    // The chunk references of the file are broken
+                    None => Some(PathEntry {
+                        match_path,
+                        response: HttpResponse {
+                            status_code: 500,
+                            headers: vec![],
+                            body: "Chunk not found".as_bytes().to_vec(),
+                            streaming_strategy: None,
+                        },
+                    }),
+                }
             }),
             None => None,
         }
     }
 
-    pub fn get_directory_path_recursive(parent_id: u64, store: &Store, path: &mut Vec<String>) {
-        let directory = store.directories.get(&parent_id);
-        if let Some(_directory) = directory {
-            path.push(_directory.name.clone());
-            if let Some(parent_id) = _directory.parent_id {
-                Self::get_directory_path_recursive(parent_id, store, path);
+    // Resolve the path with the path index, a directory is served by its `index.html`
+    // Unknown paths fall back to the `index.html` of the deepest existing directory
+    fn resolve_file<'a>(
+        path: &[&str],
+        root_id: Option<Id>,
+        store: &'a Store,
+    ) -> Option<&'a FileEntity> {
+        match Self::find_asset_by_path(&decode_path(path), root_id, store) {
+            Some(AssetWithId::File(file_id)) => store.files.get(&file_id),
+            _ => {
+                let depth = (1..=path.len())
+                    .rev()
+                    .find(|depth| {
+                        matches!(
+                            Self::find_asset_by_path(&decode_path(&path[..*depth]), root_id, store),
+                            Some(AssetWithId::Directory(_))
+                        )
+                    })
+                    .unwrap_or(0);
+
+                let mut index_path = path[..depth].to_vec();
+                index_path.push("index.html");
+                match Self::find_asset_by_path(&decode_path(&index_path), root_id, store) {
+                    Some(AssetWithId::File(file_id)) => store.files.get(&file_id),
+                    _ => None,
+                }
             }
         }
     }
@@ -200,19 +324,13 @@ impl Store {
         })
     }
 
+    // The path of the file from the path index with spaces encoded; ex: `directory/my%20file.txt`
     pub fn get_file_path(file: &FileEntity, store: &Store) -> String {
-        let mut path: Vec<String> = vec![file.name.clone()];
-
-        if let Some(parent_id) = file.parent_id {
-            Self::get_directory_path_recursive(parent_id, &store, &mut path);
-        };
-
-        let mut spaceless_path = path
-            .iter()
-            .map(|p| p.replace(" ", "%20"))
-            .collect::<Vec<String>>();
-        spaceless_path.reverse();
-        spaceless_path.join("/")
+        store
+            .file_paths
+            .get(&file.id)
+            .map(|path| path.replace(' ', "%20"))
+            .unwrap_or_default()
     }
 
     pub fn save_candid(candid: String) {
@@ -221,6 +339,6 @@ impl Store {
         use std::path::PathBuf;
 
         let dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
-        write(dir.join(format!("file_manager.did")), candid).expect("Write failed.");
+        write(dir.join("file_manager.did"), candid).expect("Write failed.");
     }
 }
@@ -0,0 +1,118 @@
+use ic_cdk::caller;
+
+use crate::{
+    helpers::image_helper::{
+        get_image_dimensions, is_resizable_image, resize_image, MAX_IMAGE_PIXELS,
+    },
+    models::{
+        asset_models::{AssetWithId, Id},
+        error_models::FileManagerError,
+        file_models::{FileEntity, FileResponse, ImageVariant},
+    },
+    store::{Store, STORE},
+};
+
+// Chunks of a variant are kept below the size limit of a single http response
+const VARIANT_CHUNK_SIZE: usize = 1_900_000;
+
+impl Store {
+    // (Re)generate the resized variants of an image file, called after the upload is complete
+    pub fn generate_image_variants(file_id: Id) -> Result<FileResponse, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            match store.files.get(&file_id) {
+                None => return Err(FileManagerError::FileNotFound(file_id)),
+                Some(_file) => {
+                    if _file.owner != Some(caller()) && !Self::is_admin(&caller(), &store) {
+                        return Err(FileManagerError::NotOwner(AssetWithId::File(file_id)));
+                    }
+                }
+            }
+
+            Self::_generate_image_variants(file_id, &mut store)?;
+
+            match store.files.get(&file_id) {
+                Some(_file) => Ok(Self::map_file_entity_to_file_response(
+                    _file.clone(),
+                    &store,
+                )),
+                None => Err(FileManagerError::FileNotFound(file_id)),
+            }
+        })
+    }
+
+    pub fn _generate_image_variants(
+        file_id: Id,
+        store: &mut Store,
+    ) -> Result<(), FileManagerError> {
+        let file = match store.files.get(&file_id) {
+            Some(_file) => _file.clone(),
+            None => return Err(FileManagerError::FileNotFound(file_id)),
+        };
+
+        if !is_resizable_image(&file.mime_type) {
+            return Err(FileManagerError::UnsupportedImage(file_id));
+        }
+
+        if !Self::is_upload_complete(&file, store) {
+            return Err(FileManagerError::UploadIncomplete(file_id));
+        }
+
+        let content = Self::get_file_content(&file, store);
+        let (width, height) = get_image_dimensions(&content, &file.mime_type)
+            .map_err(|_| FileManagerError::UnsupportedImage(file_id))?;
+        if width as u64 * height as u64 > MAX_IMAGE_PIXELS {
+            return Err(FileManagerError::ImageTooLarge {
+                file_id,
+                limit: MAX_IMAGE_PIXELS,
+            });
+        }
+
+        let images = resize_image(&content, &file.mime_type)
+            .map_err(|_| FileManagerError::UnsupportedImage(file_id))?;
+        Self::remove_image_variants(file_id, store);
+
+        let mut variants: Vec<ImageVariant> = vec![];
+        for image in images {
+            let mut chunks: Vec<Id> = vec![];
+            for bytes in image.bytes.chunks(VARIANT_CHUNK_SIZE) {
+                let chunk_id = store.chunk_id;
+                store.chunks.insert(chunk_id, bytes.to_vec());
+                chunks.push(chunk_id);
+                store.chunk_id += 1;
+            }
+
+            variants.push(ImageVariant {
+                width: image.width,
+                height: image.height,
+                mime_type: image.mime_type,
+                size: image.bytes.len() as u64,
+                chunks,
+            });
+        }
+
+        if let Some(_file) = store.files.get_mut(&file_id) {
+            _file.variants = variants;
+        }
+        Ok(())
+    }
+
+    // Remove the variants and their chunks, ex; when the content of the file changes
+    pub fn remove_image_variants(file_id: Id, store: &mut Store) {
+        if let Some(_file) = store.files.get_mut(&file_id) {
+            for variant in _file.variants.drain(..) {
+                for chunk_id in variant.chunks {
+                    store.chunks.remove(&chunk_id);
+                }
+            }
+        }
+    }
+
+    // Get the smallest variant that is at least as wide as the requested width
+    pub fn get_image_variant(file: &FileEntity, width: u32) -> Option<&ImageVariant> {
+        file.variants
+            .iter()
+            .filter(|variant| variant.width >= width)
+            .min_by_key(|variant| variant.width)
+    }
+}
@@ -0,0 +1,691 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    models::{
+        asset_models::{AssetWithId, Id},
+        directory_models::{DirectoryEntity, DirectoryStats},
+        file_models::FileEntity,
+    },
+    store::{Store, STORE},
+};
+
+impl Store {
+    // Rebuild the children and path index from the files and directories (called on upgrade)
+    pub fn rebuild_children_index() {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            store.children.clear();
+            store.paths.clear();
+            store.file_paths.clear();
+            store.directory_paths.clear();
+            store.names.clear();
+            store.directory_stats.clear();
+            store.chunk_files.clear();
+
+            for _file in store.files.values().cloned().collect::<Vec<FileEntity>>() {
+                Self::insert_child_file(&_file, &mut store);
+                Self::insert_file_chunks(&_file, &mut store);
+                Self::update_directory_stats(
+                    _file.parent_id,
+                    &Self::get_file_stats(&_file),
+                    true,
+                    &mut store,
+                );
+            }
+
+            for _directory in store
+                .directories
+                .values()
+                .cloned()
+                .collect::<Vec<DirectoryEntity>>()
+            {
+                Self::insert_child_directory(&_directory, &mut store);
+                // The contents of the directory are counted by its files and subdirectories
+                Self::update_directory_stats(
+                    _directory.parent_id,
+                    &DirectoryStats {
+                        directory_count: 1,
+                        ..Default::default()
+                    },
+                    true,
+                    &mut store,
+                );
+            }
+
+            // The paths are set from the root down once all children are known
+            for _file in Self::get_child_files(None, &store)
+                .into_iter()
+                .cloned()
+                .collect::<Vec<FileEntity>>()
+            {
+                Self::set_file_path(&_file, &mut store);
+            }
+
+            for directory_id in Self::get_child_directory_ids(None, &store) {
+                Self::set_directory_path(directory_id, &mut store);
+            }
+        })
+    }
+
+    pub fn index_file(file: &FileEntity, store: &mut Store) {
+        Self::insert_child_file(file, store);
+        Self::insert_file_chunks(file, store);
+        Self::set_file_path(file, store);
+        Self::update_directory_stats(file.parent_id, &Self::get_file_stats(file), true, store);
+    }
+
+    fn insert_child_file(file: &FileEntity, store: &mut Store) {
+        store
+            .children
+            .entry(file.parent_id)
+            .or_default()
+            .files
+            .insert(file.name.clone(), file.id);
+        Self::insert_name(&file.name, AssetWithId::File(file.id), store);
+    }
+
+    fn insert_file_chunks(file: &FileEntity, store: &mut Store) {
+        for chunk_id in &file.chunks {
+            store.chunk_files.insert(*chunk_id, file.id);
+        }
+    }
+
+    pub fn unindex_file(file: &FileEntity, store: &mut Store) {
+        if let Some(_children) = store.children.get_mut(&file.parent_id) {
+            // Only remove the entry if it points to this file
+            if _children.files.get(&file.name) == Some(&file.id) {
+                _children.files.remove(&file.name);
+            }
+        }
+        Self::remove_empty_children(file.parent_id, store);
+        for chunk_id in &file.chunks {
+            if store.chunk_files.get(chunk_id) == Some(&file.id) {
+                store.chunk_files.remove(chunk_id);
+            }
+        }
+        Self::remove_name(&file.name, &AssetWithId::File(file.id), store);
+        Self::remove_path(
+            store.file_paths.remove(&file.id),
+            AssetWithId::File(file.id),
+            store,
+        );
+        Self::update_directory_stats(file.parent_id, &Self::get_file_stats(file), false, store);
+    }
+
+    // Update the index after the name or parent of a file changed
+    pub fn reindex_file(previous: &FileEntity, file: &FileEntity, store: &mut Store) {
+        Self::unindex_file(previous, store);
+        Self::index_file(file, store);
+    }
+
+    // Also (re)sets the paths of all assets inside the directory
+    pub fn index_directory(directory: &DirectoryEntity, store: &mut Store) {
+        Self::insert_child_directory(directory, store);
+        Self::set_directory_path(directory.id, store);
+        let stats = Self::get_nested_stats(directory.id, store);
+        Self::update_directory_stats(directory.parent_id, &stats, true, store);
+    }
+
+    fn insert_child_directory(directory: &DirectoryEntity, store: &mut Store) {
+        store
+            .children
+            .entry(directory.parent_id)
+            .or_default()
+            .directories
+            .insert(directory.name.clone(), directory.id);
+        Self::insert_name(&directory.name, AssetWithId::Directory(directory.id), store);
+    }
+
+    pub fn unindex_directory(directory: &DirectoryEntity, store: &mut Store) {
+        if let Some(_children) = store.children.get_mut(&directory.parent_id) {
+            // Only remove the entry if it points to this directory
+            if _children.directories.get(&directory.name) == Some(&directory.id) {
+                _children.directories.remove(&directory.name);
+            }
+        }
+        Self::remove_empty_children(directory.parent_id, store);
+        Self::remove_name(
+            &directory.name,
+            &AssetWithId::Directory(directory.id),
+            store,
+        );
+        Self::remove_path(
+            store.directory_paths.remove(&directory.id),
+            AssetWithId::Directory(directory.id),
+            store,
+        );
+        let stats = Self::get_nested_stats(directory.id, store);
+        Self::update_directory_stats(directory.parent_id, &stats, false, store);
+    }
+
+    // Update the index after the name or parent of a directory changed
+    pub fn reindex_directory(
+        previous: &DirectoryEntity,
+        directory: &DirectoryEntity,
+        store: &mut Store,
+    ) {
+        Self::unindex_directory(previous, store);
+        Self::index_directory(directory, store);
+    }
+
+    // Add or subtract the stats from the directory and all its ancestors
+    fn update_directory_stats(
+        parent_id: Option<Id>,
+        stats: &DirectoryStats,
+        is_added: bool,
+        store: &mut Store,
+    ) {
+        let mut ancestor_id = parent_id;
+        // The depth is limited by the number of directories, in case the tree contains a cycle
+        for _ in 0..=store.directories.len() {
+            let _ancestor_id = match ancestor_id {
+                Some(_ancestor_id) => _ancestor_id,
+                None => return,
+            };
+
+            let totals = store.directory_stats.entry(_ancestor_id).or_default();
+            if is_added {
+                totals.size += stats.size;
+                totals.file_count += stats.file_count;
+                totals.directory_count += stats.directory_count;
+            } else {
+                totals.size = totals.size.saturating_sub(stats.size);
+                totals.file_count = totals.file_count.saturating_sub(stats.file_count);
+                totals.directory_count =
+                    totals.directory_count.saturating_sub(stats.directory_count);
+            }
+
+            ancestor_id = store
+                .directories
+                .get(&_ancestor_id)
+                .and_then(|_directory| _directory.parent_id);
+        }
+    }
+
+    fn get_file_stats(file: &FileEntity) -> DirectoryStats {
+        DirectoryStats {
+            size: file.size,
+            file_count: 1,
+            directory_count: 0,
+        }
+    }
+
+    // The stats a directory adds to its ancestors, its contents and the directory itself
+    fn get_nested_stats(directory_id: Id, store: &Store) -> DirectoryStats {
+        let stats = store
+            .directory_stats
+            .get(&directory_id)
+            .cloned()
+            .unwrap_or_default();
+        DirectoryStats {
+            directory_count: stats.directory_count + 1,
+            ..stats
+        }
+    }
+
+    fn remove_empty_children(parent_id: Option<Id>, store: &mut Store) {
+        if let Some(_children) = store.children.get(&parent_id) {
+            if _children.files.is_empty() && _children.directories.is_empty() {
+                store.children.remove(&parent_id);
+            }
+        }
+    }
+
+    // Full path of an asset with the name in the directory (root if None); ex: `directory/file.txt`
+    fn get_child_path(parent_id: Option<Id>, name: &str, store: &Store) -> String {
+        let name: String = name.nfc().collect();
+        match parent_id.and_then(|id| store.directory_paths.get(&id)) {
+            Some(_parent_path) => format!("{}/{}", _parent_path, name),
+            None => name,
+        }
+    }
+
+    fn set_file_path(file: &FileEntity, store: &mut Store) {
+        Self::remove_path(
+            store.file_paths.remove(&file.id),
+            AssetWithId::File(file.id),
+            store,
+        );
+
+        let path = Self::get_child_path(file.parent_id, &file.name, store);
+        store.paths.insert(path.clone(), AssetWithId::File(file.id));
+        store.file_paths.insert(file.id, path);
+    }
+
+    // Set the path of the directory and all assets inside it
+    fn set_directory_path(directory_id: Id, store: &mut Store) {
+        let previous = store.directory_paths.remove(&directory_id);
+        Self::remove_path(previous, AssetWithId::Directory(directory_id), store);
+
+        let path = match store.directories.get(&directory_id) {
+            Some(_directory) => Self::get_child_path(_directory.parent_id, &_directory.name, store),
+            None => return,
+        };
+        store
+            .paths
+            .insert(path.clone(), AssetWithId::Directory(directory_id));
+        store.directory_paths.insert(directory_id, path);
+
+        for _file in Self::get_child_files(Some(directory_id), store)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<FileEntity>>()
+        {
+            Self::set_file_path(&_file, store);
+        }
+
+        for child_id in Self::get_child_directory_ids(Some(directory_id), store) {
+            Self::set_directory_path(child_id, store);
+        }
+    }
+
+    // Only remove the path if it still points to the asset
+    fn remove_path(path: Option<String>, asset: AssetWithId, store: &mut Store) {
+        if let Some(_path) = path {
+            if store.paths.get(&_path) == Some(&asset) {
+                store.paths.remove(&_path);
+            }
+        }
+    }
+
+    fn insert_name(name: &str, asset: AssetWithId, store: &mut Store) {
+        store
+            .names
+            .entry(name.to_lowercase())
+            .or_default()
+            .insert(asset);
+    }
+
+    fn remove_name(name: &str, asset: &AssetWithId, store: &mut Store) {
+        let name = name.to_lowercase();
+        if let Some(_assets) = store.names.get_mut(&name) {
+            _assets.remove(asset);
+            if _assets.is_empty() {
+                store.names.remove(&name);
+            }
+        }
+    }
+
+    fn get_child_directory_ids(parent_id: Option<Id>, store: &Store) -> Vec<Id> {
+        store
+            .children
+            .get(&parent_id)
+            .map(|_children| _children.directories.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Find an asset by its full path (relative to the directory if set); ex: `directory/file.txt`
+    pub fn find_asset_by_path(
+        path: &str,
+        directory_id: Option<Id>,
+        store: &Store,
+    ) -> Option<AssetWithId> {
+        let full_path = match directory_id {
+            Some(_directory_id) => {
+                let directory_path = store.directory_paths.get(&_directory_id)?;
+                match path.is_empty() {
+                    true => directory_path.clone(),
+                    false => format!("{}/{}", directory_path, path),
+                }
+            }
+            None => path.to_string(),
+        };
+        store.paths.get(&full_path).cloned()
+    }
+
+    pub fn get_child_file<'a>(
+        parent_id: Option<Id>,
+        name: &str,
+        store: &'a Store,
+    ) -> Option<&'a FileEntity> {
+        store
+            .children
+            .get(&parent_id)
+            .and_then(|_children| _children.files.get(name))
+            .and_then(|file_id| store.files.get(file_id))
+    }
+
+    pub fn get_child_directory<'a>(
+        parent_id: Option<Id>,
+        name: &str,
+        store: &'a Store,
+    ) -> Option<&'a DirectoryEntity> {
+        store
+            .children
+            .get(&parent_id)
+            .and_then(|_children| _children.directories.get(name))
+            .and_then(|directory_id| store.directories.get(directory_id))
+    }
+
+    // Files in the directory (root if None) sorted by name
+    pub fn get_child_files(parent_id: Option<Id>, store: &Store) -> Vec<&FileEntity> {
+        match store.children.get(&parent_id) {
+            Some(_children) => _children
+                .files
+                .values()
+                .filter_map(|file_id| store.files.get(file_id))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // Directories in the directory (root if None) sorted by name
+    pub fn get_child_directories(parent_id: Option<Id>, store: &Store) -> Vec<&DirectoryEntity> {
+        match store.children.get(&parent_id) {
+            Some(_children) => _children
+                .directories
+                .values()
+                .filter_map(|directory_id| store.directories.get(directory_id))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    // Find a file by name, case insensitive if enabled by an admin
+    pub fn find_child_file<'a>(
+        parent_id: Option<Id>,
+        name: &str,
+        store: &'a Store,
+    ) -> Option<&'a FileEntity> {
+        let file = Self::get_child_file(parent_id, name, store);
+        if file.is_some() || !store.case_insensitive_names {
+            return file;
+        }
+
+        Self::get_child_files(parent_id, store)
+            .into_iter()
+            .find(|_file| Self::is_same_name(&_file.name, name, store))
+    }
+
+    // Find a directory by name, case insensitive if enabled by an admin
+    pub fn find_child_directory<'a>(
+        parent_id: Option<Id>,
+        name: &str,
+        store: &'a Store,
+    ) -> Option<&'a DirectoryEntity> {
+        let directory = Self::get_child_directory(parent_id, name, store);
+        if directory.is_some() || !store.case_insensitive_names {
+            return directory;
+        }
+
+        Self::get_child_directories(parent_id, store)
+            .into_iter()
+            .find(|_directory| Self::is_same_name(&_directory.name, name, store))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        models::{
+            asset_models::AssetWithId, directory_models::DirectoryEntity, file_models::FileEntity,
+        },
+        store::{Store, STORE},
+    };
+
+    fn add_directory(id: u64, parent_id: Option<u64>, store: &mut Store) -> DirectoryEntity {
+        let directory = DirectoryEntity {
+            id,
+            name: format!("directory{}", id),
+            parent_id,
+            ..Default::default()
+        };
+        store.directories.insert(id, directory.clone());
+        Store::index_directory(&directory, store);
+        directory
+    }
+
+    fn add_file(id: u64, parent_id: Option<u64>, size: u64, store: &mut Store) -> FileEntity {
+        let file = FileEntity {
+            id,
+            name: format!("file{}.txt", id),
+            size,
+            parent_id,
+            chunks: vec![id * 10, id * 10 + 1],
+            ..Default::default()
+        };
+        store.files.insert(id, file.clone());
+        Store::index_file(&file, store);
+        file
+    }
+
+    fn get_stats(directory_id: u64, store: &Store) -> (u64, u64, u64) {
+        let stats = store
+            .directory_stats
+            .get(&directory_id)
+            .cloned()
+            .unwrap_or_default();
+        (stats.size, stats.file_count, stats.directory_count)
+    }
+
+    #[test]
+    fn stats_add_file() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        add_directory(2, Some(1), &mut store);
+        add_file(1, Some(2), 100, &mut store);
+        add_file(2, Some(1), 50, &mut store);
+
+        assert_eq!(get_stats(1, &store), (150, 2, 1));
+        assert_eq!(get_stats(2, &store), (100, 1, 0));
+    }
+
+    #[test]
+    fn stats_delete_file() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        add_directory(2, Some(1), &mut store);
+        let file = add_file(1, Some(2), 100, &mut store);
+        add_file(2, Some(2), 50, &mut store);
+
+        Store::unindex_file(&file, &mut store);
+        store.files.remove(&file.id);
+
+        assert_eq!(get_stats(1, &store), (50, 1, 1));
+        assert_eq!(get_stats(2, &store), (50, 1, 0));
+    }
+
+    #[test]
+    fn stats_rename_file() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        let previous = add_file(1, Some(1), 100, &mut store);
+
+        let file = FileEntity {
+            name: "renamed.txt".to_string(),
+            ..previous.clone()
+        };
+        store.files.insert(file.id, file.clone());
+        Store::reindex_file(&previous, &file, &mut store);
+
+        assert_eq!(get_stats(1, &store), (100, 1, 0));
+    }
+
+    #[test]
+    fn stats_move_file() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        add_directory(2, Some(1), &mut store);
+        add_directory(3, None, &mut store);
+        let previous = add_file(1, Some(2), 100, &mut store);
+
+        let file = FileEntity {
+            parent_id: Some(3),
+            ..previous.clone()
+        };
+        store.files.insert(file.id, file.clone());
+        Store::reindex_file(&previous, &file, &mut store);
+
+        assert_eq!(get_stats(1, &store), (0, 0, 1));
+        assert_eq!(get_stats(2, &store), (0, 0, 0));
+        assert_eq!(get_stats(3, &store), (100, 1, 0));
+    }
+
+    #[test]
+    fn stats_move_directory() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        let previous = add_directory(2, Some(1), &mut store);
+        add_directory(3, None, &mut store);
+        add_file(1, Some(2), 100, &mut store);
+
+        let directory = DirectoryEntity {
+            parent_id: Some(3),
+            ..previous.clone()
+        };
+        store.directories.insert(directory.id, directory.clone());
+        Store::reindex_directory(&previous, &directory, &mut store);
+
+        assert_eq!(get_stats(1, &store), (0, 0, 0));
+        assert_eq!(get_stats(3, &store), (100, 1, 1));
+        assert_eq!(get_stats(2, &store), (100, 1, 0));
+    }
+
+    #[test]
+    fn stats_match_rebuild() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        add_directory(2, Some(1), &mut store);
+        add_file(1, Some(2), 100, &mut store);
+        let file = add_file(2, Some(1), 50, &mut store);
+        Store::unindex_file(&file, &mut store);
+        store.files.remove(&file.id);
+
+        let expected: Vec<(u64, u64, u64)> = (1..=2).map(|id| get_stats(id, &store)).collect();
+        STORE.with(|_store| *_store.borrow_mut() = store);
+        Store::rebuild_children_index();
+        let rebuilt: Vec<(u64, u64, u64)> =
+            STORE.with(|_store| (1..=2).map(|id| get_stats(id, &_store.borrow())).collect());
+
+        assert_eq!(expected, rebuilt);
+    }
+
+    // The derived indexes in a comparable form
+    fn get_indexes(store: &Store) -> String {
+        let children: BTreeMap<_, _> = store
+            .children
+            .iter()
+            .map(|(parent_id, _children)| (parent_id, (&_children.files, &_children.directories)))
+            .collect();
+        let paths: BTreeMap<_, _> = store.paths.iter().collect();
+        let file_paths: BTreeMap<_, _> = store.file_paths.iter().collect();
+        let directory_paths: BTreeMap<_, _> = store.directory_paths.iter().collect();
+        let chunk_files: BTreeMap<_, _> = store.chunk_files.iter().collect();
+        let stats: BTreeMap<_, _> = store
+            .directory_stats
+            .keys()
+            .map(|id| (id, get_stats(*id, store)))
+            .filter(|(_, stats)| *stats != (0, 0, 0))
+            .collect();
+        format!(
+            "{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+            children, paths, file_paths, directory_paths, store.names, stats, chunk_files
+        )
+    }
+
+    // The maintained indexes are the same as the indexes rebuilt from the assets
+    fn assert_indexes_match_rebuild(store: Store) {
+        let expected = get_indexes(&store);
+        STORE.with(|_store| *_store.borrow_mut() = store);
+        Store::rebuild_children_index();
+        STORE.with(|_store| assert_eq!(expected, get_indexes(&_store.borrow())));
+    }
+
+    #[test]
+    fn index_add_assets() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        add_directory(2, Some(1), &mut store);
+        add_file(1, Some(2), 10, &mut store);
+        add_file(2, None, 10, &mut store);
+
+        assert_eq!(
+            store.paths.get("directory1/directory2/file1.txt"),
+            Some(&AssetWithId::File(1))
+        );
+        assert_eq!(
+            store.children.get(&Some(2)).unwrap().files.get("file1.txt"),
+            Some(&1)
+        );
+        assert!(store
+            .names
+            .get("file2.txt")
+            .unwrap()
+            .contains(&AssetWithId::File(2)));
+        assert_indexes_match_rebuild(store);
+    }
+
+    #[test]
+    fn index_rename_directory() {
+        let mut store = Store::default();
+        let previous = add_directory(1, None, &mut store);
+        add_directory(2, Some(1), &mut store);
+        add_file(1, Some(2), 10, &mut store);
+
+        let directory = DirectoryEntity {
+            name: "Renamed".to_string(),
+            ..previous.clone()
+        };
+        store.directories.insert(directory.id, directory.clone());
+        Store::reindex_directory(&previous, &directory, &mut store);
+
+        assert_eq!(
+            store.file_paths.get(&1).map(String::as_str),
+            Some("Renamed/directory2/file1.txt")
+        );
+        assert!(!store.paths.contains_key("directory1/directory2/file1.txt"));
+        assert!(!store.names.contains_key("directory1"));
+        assert!(store.names.contains_key("renamed"));
+        assert_indexes_match_rebuild(store);
+    }
+
+    #[test]
+    fn index_move_and_delete() {
+        let mut store = Store::default();
+        add_directory(1, None, &mut store);
+        let previous = add_directory(2, Some(1), &mut store);
+        add_directory(3, None, &mut store);
+        add_file(1, Some(2), 10, &mut store);
+        let file = add_file(2, Some(1), 10, &mut store);
+
+        let directory = DirectoryEntity {
+            parent_id: Some(3),
+            ..previous.clone()
+        };
+        store.directories.insert(directory.id, directory.clone());
+        Store::reindex_directory(&previous, &directory, &mut store);
+
+        Store::unindex_file(&file, &mut store);
+        store.files.remove(&file.id);
+
+        assert_eq!(
+            store.paths.get("directory3/directory2/file1.txt"),
+            Some(&AssetWithId::File(1))
+        );
+        assert!(!store.children.contains_key(&Some(1)));
+        assert!(!store.names.contains_key("file2.txt"));
+        assert!(!store.chunk_files.contains_key(&20));
+        assert_eq!(store.chunk_files.get(&10), Some(&1));
+        assert_indexes_match_rebuild(store);
+    }
+
+    #[test]
+    fn index_unindex_only_own_entry() {
+        let mut store = Store::default();
+        let file = add_file(1, None, 10, &mut store);
+        // A stale entity with the same name does not remove the indexed file
+        let stale = FileEntity {
+            id: 2,
+            ..file.clone()
+        };
+        Store::unindex_file(&stale, &mut store);
+
+        assert_eq!(store.paths.get("file1.txt"), Some(&AssetWithId::File(1)));
+        assert_eq!(
+            store.children.get(&None).unwrap().files.get("file1.txt"),
+            Some(&1)
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::cmp::Ordering;
+
+use crate::{
+    models::{
+        asset_models::{Asset, AssetWithId, Id},
+        error_models::FileManagerError,
+        listing_models::{AssetPage, ListCursor, ListOptions, SortDirection, SortKey, SortValue},
+    },
+    store::{Store, STORE},
+};
+
+// Maximum number of assets in a single page
+const MAX_PAGE_SIZE: u32 = 500;
+
+// An asset in the listing with the values it is sorted by
+struct ListEntry {
+    asset: AssetWithId,
+    value: SortValue,
+}
+
+impl Store {
+    // Get a page of the direct children of the directory (root if None)
+    // Directories are listed before files, the cursor is the last asset of the previous page
+    pub fn list_directory_page(
+        directory_id: Option<Id>,
+        options: ListOptions,
+        cursor: Option<ListCursor>,
+    ) -> Result<AssetPage, FileManagerError> {
+        STORE.with(|store| {
+            let store = store.borrow();
+            if let Some(_directory_id) = directory_id {
+                if !store.directories.contains_key(&_directory_id) {
+                    return Err(FileManagerError::DirectoryNotFound(_directory_id));
+                }
+            }
+
+            if let Some(_cursor) = &cursor {
+                if !Self::is_sort_value_of(&_cursor.value, &options.sort_by) {
+                    return Err(FileManagerError::InvalidCursor);
+                }
+            }
+
+            let mut entries = Self::get_list_entries(directory_id, &options, &store);
+            entries.sort_by(|a, b| Self::compare_list_entries(a, b, &options.direction));
+
+            let limit = options.limit.clamp(1, MAX_PAGE_SIZE) as usize;
+            let mut page: Vec<ListEntry> = entries
+                .into_iter()
+                .filter(|entry| match &cursor {
+                    Some(_cursor) => {
+                        let cursor_entry = ListEntry {
+                            asset: _cursor.asset.clone(),
+                            value: _cursor.value.clone(),
+                        };
+                        Self::compare_list_entries(entry, &cursor_entry, &options.direction)
+                            == Ordering::Greater
+                    }
+                    None => true,
+                })
+                .take(limit + 1)
+                .collect();
+
+            // The extra entry tells if there is a next page
+            let next_cursor = match page.len() > limit {
+                true => {
+                    page.truncate(limit);
+                    page.last().map(|entry| ListCursor {
+                        asset: entry.asset.clone(),
+                        value: entry.value.clone(),
+                    })
+                }
+                false => None,
+            };
+
+            let assets = page
+                .into_iter()
+                .filter_map(|entry| match entry.asset {
+                    AssetWithId::File(file_id) => store.files.get(&file_id).map(|_file| {
+                        Asset::File(Self::map_file_entity_to_file_response(
+                            _file.clone(),
+                            &store,
+                        ))
+                    }),
+                    AssetWithId::Directory(directory_id) => {
+                        store.directories.get(&directory_id).map(|_directory| {
+                            Asset::Directory(Self::map_directory_entity_to_directory_response(
+                                _directory.clone(),
+                                &store,
+                            ))
+                        })
+                    }
+                })
+                .collect();
+
+            Ok(AssetPage {
+                assets,
+                next_cursor,
+            })
+        })
+    }
+
+    // The children of the directory that match the filters of the options
+    fn get_list_entries(
+        directory_id: Option<Id>,
+        options: &ListOptions,
+        store: &Store,
+    ) -> Vec<ListEntry> {
+        let is_owned = |owner| options.owner.is_none() || owner == options.owner;
+        let mut entries: Vec<ListEntry> = vec![];
+
+        // Directories have no mime type or extension to match
+        if options.mime_type.is_none() && options.extension.is_none() {
+            for _directory in Self::get_child_directories(directory_id, store) {
+                if is_owned(_directory.owner) {
+                    entries.push(ListEntry {
+                        asset: AssetWithId::Directory(_directory.id),
+                        value: match options.sort_by {
+                            SortKey::Name => SortValue::Text(_directory.name.to_lowercase()),
+                            SortKey::Size => SortValue::Number(
+                                store
+                                    .directory_stats
+                                    .get(&_directory.id)
+                                    .map_or(0, |stats| stats.size),
+                            ),
+                            SortKey::CreatedAt => SortValue::Number(_directory.created_at),
+                            SortKey::UpdatedAt => SortValue::Number(_directory.updated_at),
+                            SortKey::Type => SortValue::Text(String::new()),
+                        },
+                    });
+                }
+            }
+        }
+
+        for _file in Self::get_child_files(directory_id, store) {
+            let matches_mime_type = options
+                .mime_type
+                .as_ref()
+                .is_none_or(|prefix| _file.mime_type.starts_with(prefix.as_str()));
+            let matches_extension = options.extension.as_ref().is_none_or(|extension| {
+                _file
+                    .extension
+                    .eq_ignore_ascii_case(extension.trim_start_matches('.'))
+            });
+
+            if is_owned(_file.owner) && matches_mime_type && matches_extension {
+                entries.push(ListEntry {
+                    asset: AssetWithId::File(_file.id),
+                    value: match options.sort_by {
+                        SortKey::Name => SortValue::Text(_file.name.to_lowercase()),
+                        SortKey::Size => SortValue::Number(_file.size),
+                        SortKey::CreatedAt => SortValue::Number(_file.created_at),
+                        SortKey::UpdatedAt => SortValue::Number(_file.updated_at),
+                        SortKey::Type => SortValue::Text(_file.mime_type.clone()),
+                    },
+                });
+            }
+        }
+
+        entries
+    }
+
+    // Directories first, then by the sort value and the id so every asset has a unique position
+    fn compare_list_entries(a: &ListEntry, b: &ListEntry, direction: &SortDirection) -> Ordering {
+        let (a_rank, a_id) = Self::get_list_position(&a.asset);
+        let (b_rank, b_id) = Self::get_list_position(&b.asset);
+
+        let ordering = a.value.cmp(&b.value).then(a_id.cmp(&b_id));
+        a_rank.cmp(&b_rank).then(match direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        })
+    }
+
+    fn get_list_position(asset: &AssetWithId) -> (u8, Id) {
+        match asset {
+            AssetWithId::Directory(directory_id) => (0, *directory_id),
+            AssetWithId::File(file_id) => (1, *file_id),
+        }
+    }
+
+    fn is_sort_value_of(value: &SortValue, sort_by: &SortKey) -> bool {
+        match sort_by {
+            SortKey::Name | SortKey::Type => matches!(value, SortValue::Text(_)),
+            SortKey::Size | SortKey::CreatedAt | SortKey::UpdatedAt => {
+                matches!(value, SortValue::Number(_))
+            }
+        }
+    }
+}
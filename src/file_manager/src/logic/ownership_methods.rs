@@ -0,0 +1,207 @@
+use candid::Principal;
+use ic_cdk::{api::time, caller};
+
+use crate::{
+    models::{
+        asset_models::{Asset, AssetWithId, Id},
+        error_models::FileManagerError,
+        ownership_models::OwnershipTransfer,
+    },
+    store::{Store, STORE},
+};
+
+impl Store {
+    // Transfer the ownership of an asset to another principal
+    // Recursive transfers include the assets inside a directory that have the same owner as the directory
+    // With `require_acceptance` the transfer waits until the new owner accepts it
+    pub fn change_asset_owner(
+        owner: Principal,
+        asset: AssetWithId,
+        is_recursive: bool,
+        require_acceptance: bool,
+    ) -> Result<Asset, FileManagerError> {
+        if owner == Principal::anonymous() {
+            return Err(FileManagerError::InvalidArgument(
+                "The anonymous principal can not own assets".to_string(),
+            ));
+        }
+
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            let current_owner = Self::get_transferable_owner(&asset, &store)?;
+
+            if require_acceptance && owner != caller() {
+                store.transfers.insert(
+                    asset.clone(),
+                    OwnershipTransfer {
+                        asset: asset.clone(),
+                        from: current_owner,
+                        to: owner,
+                        requested_by: caller(),
+                        is_recursive,
+                        created_at: time(),
+                    },
+                );
+            } else {
+                store.transfers.remove(&asset);
+                Self::transfer_ownership(&asset, current_owner, owner, is_recursive, &mut store);
+            }
+
+            Self::get_asset_response(&asset, &store)
+        })
+    }
+
+    // The new owner accepts the transfer, it is void if the owner changed since it was requested
+    pub fn accept_ownership_transfer(asset: AssetWithId) -> Result<Asset, FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            let transfer = match store.transfers.remove(&asset) {
+                Some(_transfer) if _transfer.to == caller() => _transfer,
+                Some(_transfer) => {
+                    store.transfers.insert(asset.clone(), _transfer);
+                    return Err(FileManagerError::TransferNotFound(asset));
+                }
+                None => return Err(FileManagerError::TransferNotFound(asset)),
+            };
+
+            let (owner, is_protected) = Self::get_asset_owner(&asset, &store)?;
+            if is_protected || owner != transfer.from {
+                return Err(FileManagerError::TransferNotFound(asset));
+            }
+
+            Self::transfer_ownership(
+                &asset,
+                transfer.from,
+                transfer.to,
+                transfer.is_recursive,
+                &mut store,
+            );
+            Self::get_asset_response(&asset, &store)
+        })
+    }
+
+    // The new owner rejects the transfer, or the principal that requested it cancels it
+    pub fn reject_ownership_transfer(asset: AssetWithId) -> Result<(), FileManagerError> {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            match store.transfers.get(&asset) {
+                Some(_transfer)
+                    if _transfer.to == caller() || _transfer.requested_by == caller() =>
+                {
+                    store.transfers.remove(&asset);
+                    Ok(())
+                }
+                _ => Err(FileManagerError::TransferNotFound(asset)),
+            }
+        })
+    }
+
+    // Pending transfers to or requested by the caller, oldest first
+    pub fn get_ownership_transfers() -> Vec<OwnershipTransfer> {
+        STORE.with(|store| {
+            let store = store.borrow();
+            let mut transfers: Vec<OwnershipTransfer> = store
+                .transfers
+                .values()
+                .filter(|_transfer| _transfer.to == caller() || _transfer.requested_by == caller())
+                .cloned()
+                .collect();
+            transfers.sort_by_key(|_transfer| _transfer.created_at);
+            transfers
+        })
+    }
+
+    // The owner of an asset that the caller is allowed to transfer, admins can transfer any asset
+    fn get_transferable_owner(
+        asset: &AssetWithId,
+        store: &Store,
+    ) -> Result<Option<Principal>, FileManagerError> {
+        let (owner, is_protected) = Self::get_asset_owner(asset, store)?;
+        if is_protected {
+            return Err(FileManagerError::Protected(asset.clone()));
+        }
+
+        if owner != Some(caller()) && !Self::is_admin(&caller(), store) {
+            return Err(FileManagerError::NotOwner(asset.clone()));
+        }
+
+        Ok(owner)
+    }
+
+    fn get_asset_owner(
+        asset: &AssetWithId,
+        store: &Store,
+    ) -> Result<(Option<Principal>, bool), FileManagerError> {
+        match asset {
+            AssetWithId::File(file_id) => match store.files.get(file_id) {
+                Some(_file) => Ok((_file.owner, _file.is_protected)),
+                None => Err(FileManagerError::FileNotFound(*file_id)),
+            },
+            AssetWithId::Directory(directory_id) => match store.directories.get(directory_id) {
+                Some(_directory) => Ok((_directory.owner, _directory.is_protected)),
+                None => Err(FileManagerError::DirectoryNotFound(*directory_id)),
+            },
+        }
+    }
+
+    // All transferred assets get the same `updated_at`
+    fn transfer_ownership(
+        asset: &AssetWithId,
+        from: Option<Principal>,
+        to: Principal,
+        is_recursive: bool,
+        store: &mut Store,
+    ) {
+        let now = time();
+        match asset {
+            AssetWithId::File(file_id) => Self::change_file_owner(*file_id, to, now, store),
+            AssetWithId::Directory(directory_id) => {
+                Self::change_directory_owner(*directory_id, from, to, is_recursive, now, store)
+            }
+        }
+    }
+
+    fn change_file_owner(file_id: Id, owner: Principal, updated_at: u64, store: &mut Store) {
+        if let Some(_file) = store.files.get_mut(&file_id) {
+            _file.owner = Some(owner);
+            _file.updated_at = updated_at;
+        }
+    }
+
+    // Assets inside the directory that are protected or owned by someone else are kept
+    fn change_directory_owner(
+        directory_id: Id,
+        from: Option<Principal>,
+        to: Principal,
+        is_recursive: bool,
+        updated_at: u64,
+        store: &mut Store,
+    ) {
+        if let Some(_directory) = store.directories.get_mut(&directory_id) {
+            _directory.owner = Some(to);
+            _directory.updated_at = updated_at;
+        }
+
+        if !is_recursive {
+            return;
+        }
+
+        let file_ids: Vec<Id> = Self::get_child_files(Some(directory_id), store)
+            .into_iter()
+            .filter(|_file| _file.owner == from && !_file.is_protected)
+            .map(|_file| _file.id)
+            .collect();
+        for file_id in file_ids {
+            Self::change_file_owner(file_id, to, updated_at, store);
+        }
+
+        let child_ids: Vec<Id> = Self::get_child_directories(Some(directory_id), store)
+            .into_iter()
+            .filter(|_directory| _directory.owner == from && !_directory.is_protected)
+            .map(|_directory| _directory.id)
+            .collect();
+        for child_id in child_ids {
+            Self::change_directory_owner(child_id, from, to, true, updated_at, store);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{
+    helpers::name_helper::split_path,
+    models::{
+        asset_models::{Asset, AssetWithId, Id, Permission},
+        directory_models::DirectoryEntity,
+        error_models::FileManagerError,
+    },
+    store::{Store, STORE},
+};
+
+impl Store {
+    // Find the id of a file or directory by its path from the root; ex: `docs/guide/intro.md`
+    pub fn resolve_path(path: String) -> Result<AssetWithId, FileManagerError> {
+        STORE.with(|store| {
+            Self::find_asset_by_path(&split_path(&path).join("/"), None, &store.borrow())
+                .ok_or(FileManagerError::PathNotFound(path))
+        })
+    }
+
+    pub fn get_asset_by_path(path: String) -> Result<Asset, FileManagerError> {
+        Self::stat(Self::resolve_path(path)?)
+    }
+
+    pub fn rename_asset_by_path(path: String, name: String) -> Result<Asset, FileManagerError> {
+        Self::change_asset_name(name, Self::resolve_path(path)?)
+    }
+
+    // Move the asset into the directory at the parent path, an empty path is the root
+    pub fn move_asset_by_path(
+        path: String,
+        parent_path: String,
+    ) -> Result<Asset, FileManagerError> {
+        let asset = Self::resolve_path(path)?;
+        let parent_id = Self::resolve_directory_path(parent_path)?;
+        Self::change_asset_parent(parent_id, asset)
+    }
+
+    pub fn delete_asset_by_path(path: String) -> Result<(), FileManagerError> {
+        Self::delete_asset(Self::resolve_path(path)?)
+    }
+
+    // Create the directory at the path including missing parent directories (like `mkdir -p`)
+    // Directories that already exist are kept, the permission is only used for new directories
+    pub fn create_directory_by_path(
+        path: String,
+        permission: Permission,
+    ) -> Result<DirectoryEntity, FileManagerError> {
+        let names = split_path(&path);
+        if names.is_empty() {
+            return Err(FileManagerError::InvalidArgument(
+                "Path can not be empty".to_string(),
+            ));
+        }
+
+        let mut directory: Option<DirectoryEntity> = None;
+        for name in names {
+            let parent_id = directory.as_ref().map(|_directory| _directory.id);
+            let existing = STORE.with(|store| {
+                let store = store.borrow();
+                match Self::get_child_file(parent_id, &name, &store) {
+                    Some(_) => Err(FileManagerError::NotADirectory(name.clone())),
+                    None => Ok(Self::get_child_directory(parent_id, &name, &store).cloned()),
+                }
+            })?;
+
+            directory = match existing {
+                Some(_directory) => Some(_directory),
+                None => Some(Self::create_directory(name, permission.clone(), parent_id)?),
+            };
+        }
+
+        directory.ok_or(FileManagerError::InvalidArgument(
+            "Path can not be empty".to_string(),
+        ))
+    }
+
+    // The id of the directory at the path, None for the root (an empty path)
+    fn resolve_directory_path(path: String) -> Result<Option<Id>, FileManagerError> {
+        if split_path(&path).is_empty() {
+            return Ok(None);
+        }
+
+        match Self::resolve_path(path.clone())? {
+            AssetWithId::Directory(directory_id) => Ok(Some(directory_id)),
+            AssetWithId::File(_) => Err(FileManagerError::NotADirectory(path)),
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+
+use ic_cdk::caller;
+
+use crate::{
+    helpers::{
+        metadata_helper::normalize_tag,
+        mime_helper::is_text_mime_type,
+        search_helper::{get_snippet, matches_glob, tokenize},
+    },
+    models::{
+        asset_models::{Asset, AssetWithId, Id},
+        error_models::FileManagerError,
+        file_models::FileEntity,
+        metadata_models::{AssetMetadata, MetadataFilter},
+        search_models::{NameMatch, SearchPage, SearchQuery, SearchResult, TextSearchResult},
+    },
+    store::{Store, STORE},
+};
+
+// Maximum number of results in a single page
+const MAX_SEARCH_RESULTS: u32 = 500;
+
+// Maximum number of results of a full-text search
+const MAX_TEXT_SEARCH_RESULTS: usize = 50;
+
+// Only the start of large text files is indexed
+const MAX_INDEXED_SIZE: usize = 2_000_000;
+
+// Only the start of a file is scanned for a snippet, the search result may be found later
+const MAX_SNIPPET_SCAN_SIZE: usize = 64_000;
+
+impl Store {
+    // Search files and directories readable by the caller by name and attributes,
+    // the results are sorted by their path
+    // The cursor is the path of the last result of the previous page
+    pub fn search_assets(
+        query: SearchQuery,
+        cursor: Option<String>,
+    ) -> Result<SearchPage, FileManagerError> {
+        STORE.with(|store| {
+            let store = store.borrow();
+
+            // Only paths inside the scope directory match; ex: `directory/`
+            let scope_prefix = match query.scope {
+                Some(_directory_id) => match store.directory_paths.get(&_directory_id) {
+                    Some(_path) => Some(format!("{}/", _path)),
+                    None => return Err(FileManagerError::DirectoryNotFound(_directory_id)),
+                },
+                None => None,
+            };
+
+            let mut matches: Vec<(&String, &AssetWithId)> =
+                Self::get_assets_by_name(&query.name, &store)
+                    .into_iter()
+                    .filter_map(|asset| {
+                        let path = match asset {
+                            AssetWithId::File(file_id) => store.file_paths.get(file_id),
+                            AssetWithId::Directory(directory_id) => {
+                                store.directory_paths.get(directory_id)
+                            }
+                        }?;
+                        Some((path, asset))
+                    })
+                    .filter(|(path, _)| {
+                        scope_prefix
+                            .as_ref()
+                            .is_none_or(|prefix| path.starts_with(prefix.as_str()))
+                    })
+                    .filter(|(path, _)| cursor.as_ref().is_none_or(|_cursor| *path > _cursor))
+                    .filter(|(_, asset)| Self::is_asset_readable(asset, &store))
+                    .filter(|(_, asset)| Self::matches_search_query(asset, &query, &store))
+                    .collect();
+            matches.sort_by(|a, b| a.0.cmp(b.0));
+
+            let limit = query.limit.clamp(1, MAX_SEARCH_RESULTS) as usize;
+            // The extra result tells if there is a next page
+            let next_cursor = match matches.len() > limit {
+                true => {
+                    matches.truncate(limit);
+                    matches.last().map(|(path, _)| path.to_string())
+                }
+                false => None,
+            };
+
+            let results = matches
+                .into_iter()
+                .filter_map(|(path, asset)| {
+                    let asset = match asset {
+                        AssetWithId::File(file_id) => {
+                            Asset::File(Self::map_file_entity_to_file_response(
+                                store.files.get(file_id)?.clone(),
+                                &store,
+                            ))
+                        }
+                        AssetWithId::Directory(directory_id) => {
+                            Asset::Directory(Self::map_directory_entity_to_directory_response(
+                                store.directories.get(directory_id)?.clone(),
+                                &store,
+                            ))
+                        }
+                    };
+                    Some(SearchResult {
+                        asset,
+                        path: path.clone(),
+                    })
+                })
+                .collect();
+
+            Ok(SearchPage {
+                results,
+                next_cursor,
+            })
+        })
+    }
+
+    // Get the assets with a matching name from the name index, all assets if no name is given
+    fn get_assets_by_name<'a>(name: &Option<NameMatch>, store: &'a Store) -> Vec<&'a AssetWithId> {
+        match name {
+            // Names with the prefix are next to each other in the index
+            Some(NameMatch::Prefix(prefix)) => {
+                let prefix = prefix.to_lowercase();
+                store
+                    .names
+                    .range(prefix.clone()..)
+                    .take_while(|(name, _)| name.starts_with(&prefix))
+                    .flat_map(|(_, assets)| assets)
+                    .collect()
+            }
+            Some(NameMatch::Substring(value)) => {
+                let value = value.to_lowercase();
+                store
+                    .names
+                    .iter()
+                    .filter(|(name, _)| name.contains(&value))
+                    .flat_map(|(_, assets)| assets)
+                    .collect()
+            }
+            Some(NameMatch::Glob(pattern)) => {
+                let pattern = pattern.to_lowercase();
+                store
+                    .names
+                    .iter()
+                    .filter(|(name, _)| matches_glob(&pattern, name))
+                    .flat_map(|(_, assets)| assets)
+                    .collect()
+            }
+            None => store.names.values().flatten().collect(),
+        }
+    }
+
+    fn is_asset_readable(asset: &AssetWithId, store: &Store) -> bool {
+        match asset {
+            AssetWithId::File(file_id) => store.files.get(file_id).is_some_and(|_file| {
+                Self::is_readable(_file.owner, &_file.permission, caller(), store)
+            }),
+            AssetWithId::Directory(directory_id) => store
+                .directories
+                .get(directory_id)
+                .is_some_and(|_directory| {
+                    Self::is_readable(_directory.owner, &_directory.permission, caller(), store)
+                }),
+        }
+    }
+
+    fn matches_search_query(asset: &AssetWithId, query: &SearchQuery, store: &Store) -> bool {
+        let is_in_range = |value: u64, after: Option<u64>, before: Option<u64>| {
+            after.is_none_or(|_after| value >= _after)
+                && before.is_none_or(|_before| value <= _before)
+        };
+        let matches_metadata = |metadata: &AssetMetadata| {
+            query
+                .tag
+                .as_ref()
+                .is_none_or(|tag| metadata.tags.contains(&normalize_tag(tag)))
+                && query.metadata.as_ref().is_none_or(|filter| {
+                    match metadata.entries.get(filter.key.trim()) {
+                        Some(_value) => filter.value.as_ref().is_none_or(|value| value == _value),
+                        None => false,
+                    }
+                })
+        };
+
+        match asset {
+            AssetWithId::File(file_id) => match store.files.get(file_id) {
+                Some(_file) => {
+                    query
+                        .mime_type
+                        .as_ref()
+                        .is_none_or(|prefix| _file.mime_type.starts_with(prefix.as_str()))
+                        && query.extension.as_ref().is_none_or(|extension| {
+                            _file
+                                .extension
+                                .eq_ignore_ascii_case(extension.trim_start_matches('.'))
+                        })
+                        && is_in_range(_file.size, query.min_size, query.max_size)
+                        && is_in_range(_file.created_at, query.created_after, query.created_before)
+                        && is_in_range(_file.updated_at, query.updated_after, query.updated_before)
+                        && query.owner.is_none_or(|owner| _file.owner == Some(owner))
+                        && query
+                            .permission
+                            .as_ref()
+                            .is_none_or(|permission| _file.permission == *permission)
+                        && matches_metadata(&_file.metadata)
+                }
+                None => false,
+            },
+            AssetWithId::Directory(directory_id) => match store.directories.get(directory_id) {
+                // Directories have no mime type, extension or size to match
+                Some(_directory) => {
+                    query.mime_type.is_none()
+                        && query.extension.is_none()
+                        && query.min_size.is_none()
+                        && query.max_size.is_none()
+                        && is_in_range(
+                            _directory.created_at,
+                            query.created_after,
+                            query.created_before,
+                        )
+                        && is_in_range(
+                            _directory.updated_at,
+                            query.updated_after,
+                            query.updated_before,
+                        )
+                        && query
+                            .owner
+                            .is_none_or(|owner| _directory.owner == Some(owner))
+                        && query
+                            .permission
+                            .as_ref()
+                            .is_none_or(|permission| _directory.permission == *permission)
+                        && matches_metadata(&_directory.metadata)
+                }
+                None => false,
+            },
+        }
+    }
+
+    // Assets with the tag, sorted by their path
+    pub fn get_assets_by_tag(
+        tag: String,
+        cursor: Option<String>,
+    ) -> Result<SearchPage, FileManagerError> {
+        Self::search_assets(
+            SearchQuery {
+                tag: Some(tag),
+                limit: MAX_SEARCH_RESULTS,
+                ..Default::default()
+            },
+            cursor,
+        )
+    }
+
+    // Assets with the metadata key (and value), sorted by their path
+    pub fn get_assets_by_metadata(
+        filter: MetadataFilter,
+        cursor: Option<String>,
+    ) -> Result<SearchPage, FileManagerError> {
+        Self::search_assets(
+            SearchQuery {
+                metadata: Some(filter),
+                limit: MAX_SEARCH_RESULTS,
+                ..Default::default()
+            },
+            cursor,
+        )
+    }
+
+    // Search the content of text files readable by the caller, ranked by relevance
+    pub fn full_text_search(
+        query: String,
+        scope: Option<Id>,
+    ) -> Result<Vec<TextSearchResult>, FileManagerError> {
+        STORE.with(|store| {
+            let store = store.borrow();
+
+            let scope_prefix = match scope {
+                Some(_directory_id) => match store.directory_paths.get(&_directory_id) {
+                    Some(_path) => Some(format!("{}/", _path)),
+                    None => return Err(FileManagerError::DirectoryNotFound(_directory_id)),
+                },
+                None => None,
+            };
+
+            let mut terms: Vec<String> =
+                tokenize(&query).into_iter().map(|(_, term)| term).collect();
+            terms.sort();
+            terms.dedup();
+
+            // Rare terms weigh more than terms that are found in many files
+            let file_count = store.text_terms.len() as f64;
+            let mut scores: HashMap<Id, f64> = HashMap::new();
+            for term in &terms {
+                if let Some(_files) = store.text_index.get(term) {
+                    let weight = (1.0 + file_count / _files.len() as f64).ln();
+                    for (file_id, count) in _files {
+                        *scores.entry(*file_id).or_default() +=
+                            (1.0 + (*count as f64).ln()) * weight;
+                    }
+                }
+            }
+
+            let mut results: Vec<(&FileEntity, &String, f64)> = scores
+                .into_iter()
+                .filter_map(|(file_id, score)| {
+                    let file = store.files.get(&file_id)?;
+                    let path = store.file_paths.get(&file_id)?;
+                    Some((file, path, score))
+                })
+                .filter(|(file, _, _)| {
+                    Self::is_readable(file.owner, &file.permission, caller(), &store)
+                })
+                .filter(|(_, path, _)| {
+                    scope_prefix
+                        .as_ref()
+                        .is_none_or(|prefix| path.starts_with(prefix.as_str()))
+                })
+                .collect();
+            results.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.id.cmp(&b.0.id)));
+            results.truncate(MAX_TEXT_SEARCH_RESULTS);
+
+            Ok(results
+                .into_iter()
+                .map(|(file, path, score)| TextSearchResult {
+                    file: Self::map_file_entity_to_file_response(file.clone(), &store),
+                    path: path.clone(),
+                    score,
+                    snippet: get_snippet(
+                        &Self::get_text_content(file, MAX_SNIPPET_SCAN_SIZE, &store),
+                        &terms,
+                    ),
+                })
+                .collect())
+        })
+    }
+
+    // Add the content of a text file to the full-text index, replacing the previous content
+    pub fn index_file_text(file_id: Id, store: &mut Store) {
+        Self::unindex_file_text(file_id, store);
+
+        let content = match store.files.get(&file_id) {
+            Some(_file)
+                if is_text_mime_type(&_file.mime_type)
+                    && Self::is_upload_complete(_file, store) =>
+            {
+                Self::get_text_content(_file, MAX_INDEXED_SIZE, store)
+            }
+            _ => return,
+        };
+
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for (_, term) in tokenize(&content) {
+            *counts.entry(term).or_default() += 1;
+        }
+
+        for (term, count) in &counts {
+            store
+                .text_index
+                .entry(term.clone())
+                .or_default()
+                .insert(file_id, *count);
+        }
+        store
+            .text_terms
+            .insert(file_id, counts.into_keys().collect());
+    }
+
+    pub fn unindex_file_text(file_id: Id, store: &mut Store) {
+        if let Some(_terms) = store.text_terms.remove(&file_id) {
+            for term in _terms {
+                if let Some(_files) = store.text_index.get_mut(&term) {
+                    _files.remove(&file_id);
+                    if _files.is_empty() {
+                        store.text_index.remove(&term);
+                    }
+                }
+            }
+        }
+    }
+
+    // Index the text files that are not in the full-text index yet (called on upgrade)
+    pub fn index_missing_text_files() {
+        STORE.with(|store| {
+            let mut store = store.borrow_mut();
+            let file_ids: Vec<Id> = store
+                .files
+                .values()
+                .filter(|_file| is_text_mime_type(&_file.mime_type))
+                .filter(|_file| !store.text_terms.contains_key(&_file.id))
+                .map(|_file| _file.id)
+                .collect();
+
+            for file_id in file_ids {
+                Self::index_file_text(file_id, &mut store);
+            }
+        })
+    }
+
+    // The (start of the) content of a file as text
+    fn get_text_content(file: &FileEntity, max_size: usize, store: &Store) -> String {
+        let mut bytes: Vec<u8> = vec![];
+        for chunk_id in &file.chunks {
+            if bytes.len() >= max_size {
+                break;
+            }
+            if let Some(_chunk) = store.chunks.get(chunk_id) {
+                bytes.extend_from_slice(_chunk);
+            }
+        }
+        bytes.truncate(max_size);
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+}
@@ -1,30 +1,47 @@
 use candid::{candid_method, Principal};
-use ic_cdk::{caller, post_upgrade, pre_upgrade, query, storage, update};
+use ic_cdk::{caller, init, post_upgrade, pre_upgrade, query, storage, update};
 
 use crate::{
     models::asset_models::{Asset, Id, NestedAssets},
     models::{
-        asset_models::{AssetWithId, Permission},
+        archive_models::ArchiveExtraction,
+        asset_models::{AddAssetsResult, AssetWithId, Permission},
         directory_models::DirectoryEntity,
-        file_models::FileResponse,
+        error_models::FileManagerError,
+        file_models::{ContentDisposition, FileResponse},
         http_models::{
-            AssetEncoding, HttpRequest, HttpResponse, StreamingCallbackHttpResponse,
-            StreamingCallbackToken,
+            HeaderField, HeaderPreset, HeaderRule, HostMapping, HttpRequest, HttpResponse,
+            StreamingCallbackHttpResponse, StreamingCallbackToken,
         },
-        misc_models::Metadata,
+        listing_models::{AssetPage, ListCursor, ListOptions},
+        metadata_models::{MetadataChange, MetadataFilter},
+        misc_models::{Metadata, StorageUsage},
+        ownership_models::OwnershipTransfer,
+        role_models::Role,
+        search_models::{SearchPage, SearchQuery, TextSearchResult},
+        stable_models::StableStore,
     },
     store::{Store, STORE},
 };
 
+#[init]
+fn init() {
+    Store::init_admin(caller());
+}
+
 #[pre_upgrade]
 fn pre_upgrade() {
-    storage::stable_save((STORE.with(|s| s.clone()),)).unwrap();
+    let stable_store = STORE.with(|s| StableStore::from(&*s.borrow()));
+    storage::stable_save((stable_store,)).unwrap();
 }
 
 #[post_upgrade]
 fn post_upgrade() {
-    let (old_store,): (Store,) = storage::stable_restore().unwrap();
-    STORE.with(|s| *s.borrow_mut() = old_store);
+    let (stable_store,): (StableStore,) = storage::stable_restore().unwrap();
+    STORE.with(|s| *s.borrow_mut() = Store::from(stable_store));
+    Store::rebuild_children_index();
+    Store::index_missing_text_files();
+    Store::init_admin(caller());
 }
 
 #[test]
@@ -37,22 +54,90 @@ pub fn candid() {
 
 #[query]
 #[candid_method(query)]
-fn get_assets_tree(parent_id: Option<u64>, by_owner: bool) -> Vec<Asset> {
-    Store::get_assets_tree(parent_id, if by_owner { Some(caller()) } else { None })
+fn get_assets_tree(parent_id: Option<u64>, by_owner: bool, max_depth: Option<u32>) -> Vec<Asset> {
+    Store::get_assets_tree(
+        parent_id,
+        if by_owner { Some(caller()) } else { None },
+        max_depth,
+    )
+}
+
+#[query]
+#[candid_method(query)]
+fn list_directory(directory_id: Option<Id>) -> Result<Vec<Asset>, FileManagerError> {
+    Store::list_directory(directory_id)
+}
+
+#[query]
+#[candid_method(query)]
+fn list_directory_page(
+    directory_id: Option<Id>,
+    options: ListOptions,
+    cursor: Option<ListCursor>,
+) -> Result<AssetPage, FileManagerError> {
+    Store::list_directory_page(directory_id, options, cursor)
+}
+
+#[query]
+#[candid_method(query)]
+fn search_assets(
+    query: SearchQuery,
+    cursor: Option<String>,
+) -> Result<SearchPage, FileManagerError> {
+    Store::search_assets(query, cursor)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_assets_by_tag(tag: String, cursor: Option<String>) -> Result<SearchPage, FileManagerError> {
+    Store::get_assets_by_tag(tag, cursor)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_assets_by_metadata(
+    filter: MetadataFilter,
+    cursor: Option<String>,
+) -> Result<SearchPage, FileManagerError> {
+    Store::get_assets_by_metadata(filter, cursor)
+}
+
+#[query]
+#[candid_method(query)]
+fn full_text_search(
+    query: String,
+    scope: Option<Id>,
+) -> Result<Vec<TextSearchResult>, FileManagerError> {
+    Store::full_text_search(query, scope)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_asset_by_path(path: String) -> Result<Asset, FileManagerError> {
+    Store::get_asset_by_path(path)
+}
+
+#[query]
+#[candid_method(query)]
+fn resolve_path(path: String) -> Result<AssetWithId, FileManagerError> {
+    Store::resolve_path(path)
+}
+
+#[query]
+#[candid_method(query)]
+fn stat(asset: AssetWithId) -> Result<Asset, FileManagerError> {
+    Store::stat(asset)
 }
 
 #[update]
 #[candid_method(update)]
-fn add_assets(
-    parent_id: Option<Id>,
-    assets: Vec<NestedAssets>,
-) -> Result<Vec<(FileResponse, String)>, (Vec<Asset>, String)> {
+fn add_assets(parent_id: Option<Id>, assets: Vec<NestedAssets>) -> AddAssetsResult {
     Store::add_assets(parent_id, assets)
 }
 
 #[update]
 #[candid_method(update)]
-fn add_chunks(data: Vec<(Id, Vec<u8>)>) {
+fn add_chunks(data: Vec<(Id, Vec<u8>)>) -> Result<(), FileManagerError> {
     Store::add_chunks(data)
 }
 
@@ -62,41 +147,217 @@ fn create_directory(
     name: String,
     permission: Permission,
     parent_id: Option<Id>,
-) -> Result<DirectoryEntity, String> {
+) -> Result<DirectoryEntity, FileManagerError> {
     Store::create_directory(name, permission, parent_id)
 }
 
 #[update]
 #[candid_method(update)]
-fn change_asset_name(name: String, asset: AssetWithId) -> Result<Asset, String> {
+fn create_directory_by_path(
+    path: String,
+    permission: Permission,
+) -> Result<DirectoryEntity, FileManagerError> {
+    Store::create_directory_by_path(path, permission)
+}
+
+#[update]
+#[candid_method(update)]
+fn change_asset_name(name: String, asset: AssetWithId) -> Result<Asset, FileManagerError> {
     Store::change_asset_name(name, asset)
 }
 
 #[update]
 #[candid_method(update)]
-fn change_asset_parent(parent_id: Option<Id>, asset: AssetWithId) -> Result<Asset, String> {
+fn change_asset_parent(
+    parent_id: Option<Id>,
+    asset: AssetWithId,
+) -> Result<Asset, FileManagerError> {
     Store::change_asset_parent(parent_id, asset)
 }
 
 #[update]
 #[candid_method(update)]
-fn change_asset_owner(owner: Principal, asset: AssetWithId) -> Result<Asset, String> {
-    // Not implemented yet
-    Err("Not implemented yet".to_string())
+fn rename_asset_by_path(path: String, name: String) -> Result<Asset, FileManagerError> {
+    Store::rename_asset_by_path(path, name)
+}
+
+#[update]
+#[candid_method(update)]
+fn move_asset_by_path(path: String, parent_path: String) -> Result<Asset, FileManagerError> {
+    Store::move_asset_by_path(path, parent_path)
+}
+
+#[update]
+#[candid_method(update)]
+fn copy_asset(parent_id: Option<Id>, asset: AssetWithId) -> Result<Asset, FileManagerError> {
+    Store::copy_asset(parent_id, asset)
+}
+
+#[update]
+#[candid_method(update)]
+fn extract_archive(
+    file_id: Id,
+    parent_id: Option<Id>,
+) -> Result<ArchiveExtraction, FileManagerError> {
+    Store::extract_archive(file_id, parent_id)
+}
+
+#[update]
+#[candid_method(update)]
+fn change_asset_owner(
+    owner: Principal,
+    asset: AssetWithId,
+    recursive: bool,
+    require_acceptance: bool,
+) -> Result<Asset, FileManagerError> {
+    Store::change_asset_owner(owner, asset, recursive, require_acceptance)
+}
+
+#[update]
+#[candid_method(update)]
+fn accept_ownership_transfer(asset: AssetWithId) -> Result<Asset, FileManagerError> {
+    Store::accept_ownership_transfer(asset)
 }
 
 #[update]
 #[candid_method(update)]
-fn change_asset_permission(permission: Permission, asset: AssetWithId) -> Result<Asset, String> {
+fn reject_ownership_transfer(asset: AssetWithId) -> Result<(), FileManagerError> {
+    Store::reject_ownership_transfer(asset)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_ownership_transfers() -> Vec<OwnershipTransfer> {
+    Store::get_ownership_transfers()
+}
+
+#[update]
+#[candid_method(update)]
+fn change_asset_permission(
+    permission: Permission,
+    asset: AssetWithId,
+) -> Result<Asset, FileManagerError> {
     Store::change_asset_permission(permission, asset)
 }
 
 #[update]
 #[candid_method(update)]
-fn delete_asset(asset: AssetWithId) -> Result<(), String> {
+fn change_asset_headers(
+    headers: Vec<HeaderField>,
+    preset: Option<HeaderPreset>,
+    asset: AssetWithId,
+) -> Result<Asset, FileManagerError> {
+    Store::change_asset_headers(headers, preset, asset)
+}
+
+#[update]
+#[candid_method(update)]
+fn change_asset_metadata(
+    change: MetadataChange,
+    asset: AssetWithId,
+) -> Result<Asset, FileManagerError> {
+    Store::change_asset_metadata(change, asset)
+}
+
+#[update]
+#[candid_method(update)]
+fn change_file_disposition(
+    disposition: ContentDisposition,
+    file_id: Id,
+) -> Result<FileResponse, FileManagerError> {
+    Store::change_file_disposition(file_id, disposition)
+}
+
+#[update]
+#[candid_method(update)]
+fn load_headers_file(directory_id: Option<Id>) -> Result<Vec<HeaderRule>, FileManagerError> {
+    Store::load_headers_file(directory_id)
+}
+
+#[update]
+#[candid_method(update)]
+fn delete_asset(asset: AssetWithId) -> Result<(), FileManagerError> {
     Store::delete_asset(asset)
 }
 
+#[update]
+#[candid_method(update)]
+fn delete_asset_by_path(path: String) -> Result<(), FileManagerError> {
+    Store::delete_asset_by_path(path)
+}
+
+#[update]
+#[candid_method(update)]
+fn set_case_insensitive_names(enabled: bool) -> Result<(), FileManagerError> {
+    Store::set_case_insensitive_names(enabled)
+}
+
+#[update]
+#[candid_method(update)]
+fn grant_role(principal: Principal, role: Role) -> Result<(), FileManagerError> {
+    Store::grant_role(principal, role)
+}
+
+#[update]
+#[candid_method(update)]
+fn revoke_role(principal: Principal) -> Result<(), FileManagerError> {
+    Store::revoke_role(principal)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_roles() -> Result<Vec<(Principal, Role)>, FileManagerError> {
+    Store::get_roles()
+}
+
+#[query]
+#[candid_method(query)]
+fn get_role() -> Option<Role> {
+    Store::get_role()
+}
+
+#[update]
+#[candid_method(update)]
+fn set_host_mapping(host: String, directory_id: Id) -> Result<HostMapping, FileManagerError> {
+    Store::set_host_mapping(host, directory_id)
+}
+
+#[update]
+#[candid_method(update)]
+fn remove_host_mapping(host: String) -> Result<(), FileManagerError> {
+    Store::remove_host_mapping(host)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_host_mappings() -> Vec<HostMapping> {
+    Store::get_host_mappings()
+}
+
+#[update]
+#[candid_method(update)]
+fn generate_image_variants(file_id: Id) -> Result<FileResponse, FileManagerError> {
+    Store::generate_image_variants(file_id)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_chunk(file_id: Id, index: u64) -> Result<Vec<u8>, FileManagerError> {
+    Store::get_chunk(file_id, index)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_file_range(file_id: Id, offset: u64, length: u64) -> Result<Vec<u8>, FileManagerError> {
+    Store::get_file_range(file_id, offset, length)
+}
+
+#[query]
+#[candid_method(query)]
+fn get_storage_breakdown(directory_id: Option<Id>) -> Result<Vec<StorageUsage>, FileManagerError> {
+    Store::get_storage_breakdown(directory_id)
+}
+
 #[query]
 #[candid_method(query)]
 fn get_metadata() -> Metadata {
@@ -111,37 +372,6 @@ fn http_request(req: HttpRequest) -> HttpResponse {
 
 #[query]
 #[candid_method(query)]
-fn http_request_streaming_callback(data: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
-    STORE.with(|store| -> StreamingCallbackHttpResponse {
-        let store = store.borrow();
-        let file = store.files.get(&data.file_id);
-        match file {
-            Some(_file) => {
-                let encoding = AssetEncoding {
-                    content_chunks: _file.chunks.clone(),
-                    total_length: _file.size as u128,
-                };
-
-                let body = store
-                    .chunks
-                    .get(&encoding.content_chunks[data.chunk_index])
-                    .unwrap()
-                    .clone();
-
-                StreamingCallbackHttpResponse {
-                    token: Store::create_token(
-                        &data.file_id,
-                        data.chunk_index,
-                        &encoding,
-                        &data.headers,
-                    ),
-                    body,
-                }
-            }
-            None => StreamingCallbackHttpResponse {
-                token: None,
-                body: vec![],
-            },
-        }
-    })
+fn http_request_streaming_callback(token: StreamingCallbackToken) -> StreamingCallbackHttpResponse {
+    Store::http_request_streaming_callback(token)
 }
@@ -0,0 +1,18 @@
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+use super::asset_models::Id;
+
+// Progress of extracting an archive file, large archives are extracted over multiple calls
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct ArchiveExtraction {
+    pub file_id: Id,
+    pub version: u64, // the extraction restarts when the content of the archive changes
+    pub parent_id: Option<Id>,
+    pub owner: Principal,
+    pub entry_index: u64,
+    // Byte offset of the next entry in a tar archive, missing in extractions of older versions
+    pub entry_offset: Option<u64>,
+    pub entry_count: u64,
+    pub is_complete: bool,
+}
@@ -1,8 +1,11 @@
+use std::collections::BTreeMap;
+
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
 
 use super::{
     directory_models::{DirectoryResponse, PostDirectory},
+    error_models::FileManagerError,
     file_models::{FileResponse, PostFile},
 };
 
@@ -10,6 +13,8 @@ pub type Id = u64;
 pub type ChunkCount = u64;
 pub type Path = Vec<String>;
 pub type Manifest = Vec<Id>;
+// The added files with their origin path
+pub type AddAssetsResult = Result<Vec<(FileResponse, String)>, FileManagerError>;
 
 #[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
 pub struct NestedAssets {
@@ -23,34 +28,33 @@ pub enum Asset {
     Directory(DirectoryResponse),
 }
 
-#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, CandidType, Serialize, Deserialize,
+)]
 pub enum AssetWithId {
     File(Id),
     Directory(Id),
 }
 
-#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
 pub enum PostAsset {
+    #[default]
     None,
     File(PostFile),
     Directory(PostDirectory),
 }
 
-impl Default for PostAsset {
-    fn default() -> Self {
-        PostAsset::None
-    }
-}
-
-#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
 pub enum Permission {
-    Public,       // public file
-    Private,      // private file
+    Public, // public file
+    #[default]
+    Private, // private file
     Origin(Path), // http origin to access the resource
 }
 
-impl Default for Permission {
-    fn default() -> Self {
-        Permission::Private
-    }
+// The files and directories in a directory by their name
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct ChildAssets {
+    pub files: BTreeMap<String, Id>,
+    pub directories: BTreeMap<String, Id>,
 }
@@ -1,7 +1,11 @@
 use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
-use super::asset_models::{Asset, Id, Permission};
+use super::{
+    asset_models::{Asset, Id, Permission},
+    http_models::{HeaderField, HeaderRule},
+    metadata_models::AssetMetadata,
+};
 
 #[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
 pub struct DirectoryEntity {
@@ -9,6 +13,9 @@ pub struct DirectoryEntity {
     pub name: String,
     pub parent_id: Option<Id>,
     pub permission: Permission,
+    pub headers: Vec<HeaderField>,
+    pub header_rules: Vec<HeaderRule>,
+    pub metadata: AssetMetadata,
     pub is_protected: bool,
     pub owner: Option<Principal>,
     pub created_at: u64,
@@ -20,6 +27,7 @@ pub struct PostDirectory {
     pub name: String,
     pub parent_id: Option<Id>,
     pub permission: Permission,
+    pub metadata: AssetMetadata,
     pub children: Vec<Asset>,
 }
 
@@ -28,8 +36,14 @@ pub struct DirectoryResponse {
     pub id: u64,
     pub name: String,
     pub parent_id: Option<Id>,
-    pub children: Vec<Asset>, // only used when getting the directory
+    pub children: Vec<Asset>,  // only used when getting the directory
+    pub file_count: u64,       // number of files directly in the directory
+    pub directory_count: u64,  // number of directories directly in the directory
+    pub stats: DirectoryStats, // totals of everything inside the directory
     pub permission: Permission,
+    pub headers: Vec<HeaderField>,
+    pub header_rules: Vec<HeaderRule>,
+    pub metadata: AssetMetadata,
     pub is_protected: bool,
     pub owner: Option<Principal>,
     pub created_at: u64,
@@ -41,3 +55,11 @@ pub struct NestedDirectories {
     pub name: String,
     pub children: Vec<NestedDirectories>,
 }
+
+// Totals of all files and directories inside a directory (recursive)
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct DirectoryStats {
+    pub size: u64,
+    pub file_count: u64,
+    pub directory_count: u64,
+}
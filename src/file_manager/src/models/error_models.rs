@@ -0,0 +1,145 @@
+use std::fmt;
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+use super::asset_models::{AssetWithId, Id};
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum FileManagerError {
+    FileNotFound(Id),
+    DirectoryNotFound(Id),
+    ChunkNotFound {
+        file_id: Id,
+        index: u64,
+    },
+    PathNotFound(String),
+    HostNotFound(String),
+    NotOwner(AssetWithId),
+    NotReadable(AssetWithId),
+    NotAdmin,
+    // Only writers can add assets to the root
+    NotWriter,
+    Protected(AssetWithId),
+    // Existing assets that could not be replaced by `add_assets`
+    ProtectedAssets(Vec<AssetWithId>),
+    // No pending ownership transfer of the asset for the caller, or the owner changed since
+    TransferNotFound(AssetWithId),
+    NameConflict {
+        name: String,
+        parent_id: Option<Id>,
+    },
+    InvalidName {
+        name: String,
+        reason: InvalidNameReason,
+    },
+    NotADirectory(String),
+    // A directory can not be moved or copied into itself or one of its subdirectories
+    InvalidParent {
+        directory_id: Id,
+        parent_id: Id,
+    },
+    UploadIncomplete(Id),
+    ContentChanged(Id),
+    UnsupportedImage(Id),
+    ImageTooLarge {
+        file_id: Id,
+        limit: u64,
+    },
+    InvalidArchive {
+        file_id: Id,
+        reason: String,
+    },
+    InvalidHeader(String),
+    // Metadata key or tag that is empty, too long or has an invalid value
+    InvalidMetadata(String),
+    ProtectedHeader(String),
+    InvalidHost(String),
+    QuotaExceeded {
+        limit: u64,
+    },
+    InvalidCursor,
+    InvalidArgument(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum InvalidNameReason {
+    Empty,
+    TooLong { max_length: u64 },
+    InvalidCharacters,
+    Reserved,
+}
+
+// Readable messages, used as the body of failed archive downloads
+impl fmt::Display for FileManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileNotFound(file_id) => write!(f, "File {} not found", file_id),
+            Self::DirectoryNotFound(directory_id) => {
+                write!(f, "Directory {} not found", directory_id)
+            }
+            Self::ChunkNotFound { file_id, index } => {
+                write!(f, "Chunk {} of file {} not found", index, file_id)
+            }
+            Self::PathNotFound(path) => write!(f, "No asset found at {}", path),
+            Self::HostNotFound(host) => write!(f, "Host mapping for {} not found", host),
+            Self::NotOwner(asset) => write!(f, "{} is not owned by you", get_asset_label(asset)),
+            Self::NotReadable(asset) => {
+                write!(f, "{} is not readable by you", get_asset_label(asset))
+            }
+            Self::NotAdmin => write!(f, "Only admins can do this"),
+            Self::NotWriter => write!(f, "Only writers can add assets to the root"),
+            Self::Protected(asset) => write!(f, "{} is protected", get_asset_label(asset)),
+            Self::ProtectedAssets(_) => write!(f, "Some assets are protected or not owned by you"),
+            Self::TransferNotFound(asset) => write!(
+                f,
+                "No ownership transfer of {} found",
+                get_asset_label(asset).to_lowercase()
+            ),
+            Self::NameConflict { name, .. } => write!(f, "Asset with name {} already exists", name),
+            Self::InvalidName { name, reason } => match reason {
+                InvalidNameReason::Empty => write!(f, "Name can not be empty"),
+                InvalidNameReason::TooLong { max_length } => {
+                    write!(f, "Name can not be longer than {} bytes", max_length)
+                }
+                InvalidNameReason::InvalidCharacters => {
+                    write!(f, "Name {} contains invalid characters", name)
+                }
+                InvalidNameReason::Reserved => write!(f, "Name {} is reserved", name),
+            },
+            Self::NotADirectory(path) => write!(f, "{} is not a directory", path),
+            Self::InvalidParent { directory_id, .. } => write!(
+                f,
+                "Directory {} can not be moved into itself or one of its subdirectories",
+                directory_id
+            ),
+            Self::UploadIncomplete(file_id) => {
+                write!(f, "Upload of file {} is not complete", file_id)
+            }
+            Self::ContentChanged(file_id) => write!(f, "File {} changed", file_id),
+            Self::UnsupportedImage(file_id) => {
+                write!(f, "File {} is not a supported image", file_id)
+            }
+            Self::ImageTooLarge { file_id, limit } => {
+                write!(f, "Image {} is larger than {} pixels", file_id, limit)
+            }
+            Self::InvalidArchive { file_id, reason } => {
+                write!(f, "File {} is not a valid archive: {}", file_id, reason)
+            }
+            Self::InvalidHeader(name) => write!(f, "Invalid header '{}'", name),
+            Self::InvalidMetadata(key) => write!(f, "Invalid metadata '{}'", key),
+            Self::ProtectedHeader(name) => write!(f, "Header '{}' can not be overwritten", name),
+            Self::InvalidHost(host) => write!(f, "Invalid host {}", host),
+            Self::QuotaExceeded { limit } => write!(f, "Limit of {} exceeded", limit),
+            Self::InvalidCursor => write!(f, "Cursor does not match the sort key"),
+            Self::InvalidArgument(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+fn get_asset_label(asset: &AssetWithId) -> String {
+    match asset {
+        AssetWithId::File(file_id) => format!("File {}", file_id),
+        AssetWithId::Directory(directory_id) => format!("Directory {}", directory_id),
+    }
+}
@@ -1,7 +1,11 @@
 use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
-use super::asset_models::{Id, Manifest, Permission};
+use super::{
+    asset_models::{Id, Manifest, Permission},
+    http_models::HeaderField,
+    metadata_models::AssetMetadata,
+};
 
 #[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
 pub struct FileEntity {
@@ -13,7 +17,11 @@ pub struct FileEntity {
     pub permission: Permission,
     pub parent_id: Option<Id>,
     pub chunks: Manifest,
-    pub metadata: Option<String>,
+    pub version: u64, // incremented every time the content of the file changes
+    pub variants: Vec<ImageVariant>,
+    pub metadata: AssetMetadata,
+    pub headers: Vec<HeaderField>,
+    pub disposition: ContentDisposition,
     pub is_protected: bool,
     pub owner: Option<Principal>,
     pub created_at: u64,
@@ -29,7 +37,7 @@ pub struct PostFile {
     pub permission: Permission,
     pub parent_id: Option<Id>,
     pub chunk_count: u64,
-    pub metadata: Option<String>,
+    pub metadata: AssetMetadata,
     pub origin_path: String,
 }
 
@@ -43,10 +51,31 @@ pub struct FileResponse {
     pub permission: Permission,
     pub parent_id: Option<Id>,
     pub chunks: Manifest,
+    pub version: u64,
+    pub variants: Vec<ImageVariant>,
     pub path: String,
-    pub metadata: Option<String>,
+    pub metadata: AssetMetadata,
+    pub headers: Vec<HeaderField>,
+    pub disposition: ContentDisposition,
     pub is_protected: bool,
     pub owner: Option<Principal>,
     pub created_at: u64,
     pub updated_at: u64,
 }
+
+// Resized version of an image file, served with `?w=<width>`
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct ImageVariant {
+    pub width: u32,
+    pub height: u32,
+    pub mime_type: String,
+    pub size: u64,
+    pub chunks: Manifest,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub enum ContentDisposition {
+    #[default]
+    Inline, // let the browser decide to display or download the file
+    Attachment, // always download the file with its original name
+}
@@ -1,8 +1,28 @@
 use candid::{CandidType, Deserialize, Func};
+use serde::Serialize;
 
-#[derive(CandidType, Deserialize, Clone)]
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
 pub struct HeaderField(pub String, pub String);
 
+// Headers applied to every path matching the pattern, loaded from a `_headers` file
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct HeaderRule {
+    pub pattern: String,
+    pub headers: Vec<HeaderField>,
+}
+
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub enum HeaderPreset {
+    SecureWebsite, // common security headers for serving a website
+}
+
+// Serve the directory as the document root for requests with this `host` header
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct HostMapping {
+    pub host: String,
+    pub directory_id: u64,
+}
+
 #[derive(CandidType, Deserialize, Clone)]
 pub struct HttpRequest {
     pub url: String,
@@ -30,16 +50,51 @@ pub enum StreamingStrategy {
 #[derive(CandidType, Deserialize, Clone)]
 pub struct StreamingCallbackToken {
     pub file_id: u64,
+    pub version: u64,                  // version of the file when the stream started
+    pub variant: Option<u32>,          // width of the image variant that is streamed
+    pub archive: Option<ArchiveToken>, // set when a directory is streamed as zip archive
+
     pub headers: Vec<HeaderField>,
     pub chunk_index: usize,
 }
 
+// Progress of a zip archive that is streamed, the entries are fixed when the download starts
+#[derive(CandidType, Deserialize, Clone)]
+pub struct ArchiveToken {
+    pub directory_id: u64,
+    pub entries: Vec<ArchiveEntry>,
+    pub entry_index: usize,
+    pub part_index: usize, // 0: local header, 1..=chunks: file chunks, after: data descriptor
+    // Bytes of the current chunk that are already sent, chunks can be split over responses
+    pub chunk_offset: usize,
+    pub offset: u64, // bytes of the archive that are already sent
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct ArchiveEntry {
+    pub file_id: Option<u64>, // None for directories
+    pub version: u64,
+    pub path: String, // path inside the archive, directories end with a `/`
+    pub size: u64,
+    pub modified_at: u64,
+    pub crc32: u32,  // calculated while the file is streamed
+    pub offset: u64, // offset of the local header in the archive
+}
+
 #[derive(CandidType, Deserialize, Clone)]
 pub struct StreamingCallbackHttpResponse {
     pub body: Vec<u8>,
     pub token: Option<StreamingCallbackToken>,
 }
 
+// Options to serve a file with, taken from the query parameters of the request
+#[derive(Clone, Debug, Default)]
+pub struct ServeOptions {
+    pub download: bool,     // `?download`
+    pub width: Option<u32>, // `?w=256`
+    pub zip: bool,          // `?zip`, download a directory as zip archive
+}
+
 #[derive(CandidType, Deserialize, Clone)]
 pub struct AssetEncoding {
     pub content_chunks: Vec<u64>,
@@ -0,0 +1,51 @@
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+use super::asset_models::{Asset, AssetWithId};
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub enum SortKey {
+    #[default]
+    Name,
+    Size,
+    CreatedAt,
+    UpdatedAt,
+    Type, // mime type of files
+}
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct ListOptions {
+    pub sort_by: SortKey,
+    pub direction: SortDirection,
+    pub mime_type: Option<String>, // prefix of the mime type, ex: `image/` (only files match)
+    pub extension: Option<String>, // only files match
+    pub owner: Option<Principal>,
+    pub limit: u32,
+}
+
+// The value of the sort key of an asset
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, CandidType, Serialize, Deserialize)]
+pub enum SortValue {
+    Text(String),
+    Number(u64),
+}
+
+// Position after the last asset of a page, stays valid when assets are added or removed
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct ListCursor {
+    pub value: SortValue,
+    pub asset: AssetWithId,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct AssetPage {
+    pub assets: Vec<Asset>,
+    pub next_cursor: Option<ListCursor>, // None on the last page
+}
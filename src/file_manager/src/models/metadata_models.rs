@@ -0,0 +1,34 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, CandidType, Serialize, Deserialize)]
+pub enum MetadataValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+// User defined metadata of a file or directory
+#[derive(Clone, Debug, Default, PartialEq, CandidType, Serialize, Deserialize)]
+pub struct AssetMetadata {
+    pub entries: BTreeMap<String, MetadataValue>,
+    pub tags: BTreeSet<String>, // lowercase, ex: `invoice`
+}
+
+// Changes to the metadata of an asset, entries and tags that are not mentioned are kept
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct MetadataChange {
+    pub set: Vec<(String, MetadataValue)>,
+    pub remove: Vec<String>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+// Matches assets with the metadata key, and the value if set
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct MetadataFilter {
+    pub key: String,
+    pub value: Option<MetadataValue>,
+}
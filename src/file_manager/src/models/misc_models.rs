@@ -1,4 +1,6 @@
 use candid::{CandidType, Deserialize};
+
+use super::{asset_models::AssetWithId, directory_models::DirectoryStats};
 use serde::Serialize;
 
 #[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
@@ -11,3 +13,11 @@ pub struct Metadata {
     pub files_combined_bytes: u64,
     pub version: String,
 }
+
+// Storage used by a file or directory in a directory
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub asset: AssetWithId,
+    pub name: String,
+    pub stats: DirectoryStats, // a file counts as a single file of its size
+}
@@ -0,0 +1,15 @@
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+use super::asset_models::AssetWithId;
+
+// Ownership transfer that waits until the new owner accepts or rejects it
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct OwnershipTransfer {
+    pub asset: AssetWithId,
+    pub from: Option<Principal>, // owner when the transfer was requested
+    pub to: Principal,
+    pub requested_by: Principal, // the owner or an admin
+    pub is_recursive: bool,      // also transfer the assets inside a directory with the same owner
+    pub created_at: u64,
+}
@@ -0,0 +1,12 @@
+use candid::{CandidType, Deserialize};
+use serde::Serialize;
+
+// Every role includes the permissions of the roles before it
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, CandidType, Serialize, Deserialize,
+)]
+pub enum Role {
+    Reader, // can read private assets of others
+    Writer, // can add assets to the root
+    Admin,  // can manage roles and settings, and act as the owner of every asset
+}
@@ -0,0 +1,55 @@
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+use super::{
+    asset_models::{Asset, Id, Permission},
+    file_models::FileResponse,
+    metadata_models::MetadataFilter,
+};
+
+// How the name of an asset is matched (case insensitive)
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub enum NameMatch {
+    Substring(String),
+    Prefix(String),
+    Glob(String), // ex: `*.md`
+}
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub name: Option<NameMatch>,
+    pub mime_type: Option<String>, // prefix of the mime type, ex: `image/` (only files match)
+    pub extension: Option<String>, // only files match
+    pub min_size: Option<u64>,     // only files match
+    pub max_size: Option<u64>,     // only files match
+    pub created_after: Option<u64>,
+    pub created_before: Option<u64>,
+    pub updated_after: Option<u64>,
+    pub updated_before: Option<u64>,
+    pub owner: Option<Principal>,
+    pub permission: Option<Permission>,
+    pub tag: Option<String>,
+    pub metadata: Option<MetadataFilter>,
+    pub scope: Option<Id>, // only search inside this directory
+    pub limit: u32,
+}
+
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub asset: Asset,
+    pub path: String, // full path from the root, ex: `directory/file.txt`
+}
+
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct SearchPage {
+    pub results: Vec<SearchResult>,
+    pub next_cursor: Option<String>, // path of the last result, None on the last page
+}
+
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct TextSearchResult {
+    pub file: FileResponse,
+    pub path: String,    // full path from the root, ex: `directory/file.txt`
+    pub score: f64,      // higher is more relevant
+    pub snippet: String, // part of the content around the first match
+}
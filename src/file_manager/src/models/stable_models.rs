@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use candid::{CandidType, Deserialize, Principal};
+use serde::Serialize;
+
+use super::{
+    archive_models::ArchiveExtraction,
+    asset_models::{Id, Manifest, Permission},
+    directory_models::DirectoryEntity,
+    file_models::{ContentDisposition, FileEntity, ImageVariant},
+    http_models::{HeaderField, HeaderRule},
+    metadata_models::{AssetMetadata, MetadataValue},
+    ownership_models::OwnershipTransfer,
+    role_models::Role,
+};
+use crate::{helpers::metadata_helper::MAX_METADATA_TEXT_LENGTH, store::Store};
+
+// Key of the opaque metadata string of older versions in the typed metadata
+const LEGACY_METADATA_KEY: &str = "metadata";
+
+// Layout of the store in stable memory
+// The fields of the first release keep their type, fields added later are optional
+// so the state of every earlier release can be restored
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct StableStore {
+    pub file_id: Id,
+    pub files: HashMap<Id, StableFileEntity>,
+    pub directory_id: Id,
+    pub directories: HashMap<Id, StableDirectoryEntity>,
+    pub chunk_id: Id,
+    pub chunks: HashMap<Id, Vec<u8>>,
+    pub whitelist: Vec<Principal>, // the admins
+    pub version: String,
+
+    pub header_rules: Option<Vec<HeaderRule>>,
+    pub hosts: Option<HashMap<String, Id>>,
+    pub extractions: Option<HashMap<Id, ArchiveExtraction>>,
+    pub case_insensitive_names: Option<bool>,
+    pub transfers: Option<Vec<OwnershipTransfer>>,
+    pub roles: Option<Vec<(Principal, Role)>>,
+}
+
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct StableFileEntity {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub mime_type: String,
+    pub extension: String,
+    pub permission: Permission,
+    pub parent_id: Option<Id>,
+    pub chunks: Manifest,
+    pub metadata: Option<String>, // opaque metadata of older versions
+    pub is_protected: bool,
+    pub owner: Option<Principal>,
+    pub created_at: u64,
+    pub updated_at: u64,
+
+    pub version: Option<u64>,
+    pub variants: Option<Vec<ImageVariant>>,
+    pub asset_metadata: Option<AssetMetadata>,
+    pub headers: Option<Vec<HeaderField>>,
+    pub disposition: Option<ContentDisposition>,
+}
+
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct StableDirectoryEntity {
+    pub id: u64,
+    pub name: String,
+    pub parent_id: Option<Id>,
+    pub permission: Permission,
+    pub is_protected: bool,
+    pub owner: Option<Principal>,
+    pub created_at: u64,
+    pub updated_at: u64,
+
+    pub headers: Option<Vec<HeaderField>>,
+    pub header_rules: Option<Vec<HeaderRule>>,
+    pub asset_metadata: Option<AssetMetadata>,
+}
+
+impl From<&Store> for StableStore {
+    fn from(store: &Store) -> Self {
+        let admins = store
+            .whitelist
+            .iter()
+            .filter(|(_, role)| **role == Role::Admin)
+            .map(|(principal, _)| *principal)
+            .collect();
+
+        Self {
+            file_id: store.file_id,
+            files: store
+                .files
+                .iter()
+                .map(|(id, file)| (*id, file.clone().into()))
+                .collect(),
+            directory_id: store.directory_id,
+            directories: store
+                .directories
+                .iter()
+                .map(|(id, directory)| (*id, directory.clone().into()))
+                .collect(),
+            chunk_id: store.chunk_id,
+            chunks: store.chunks.clone(),
+            whitelist: admins,
+            version: store.version.clone(),
+            header_rules: Some(store.header_rules.clone()),
+            hosts: Some(store.hosts.clone()),
+            extractions: Some(store.extractions.clone()),
+            case_insensitive_names: Some(store.case_insensitive_names),
+            transfers: Some(store.transfers.values().cloned().collect()),
+            roles: Some(
+                store
+                    .whitelist
+                    .iter()
+                    .map(|(principal, role)| (*principal, *role))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<StableStore> for Store {
+    fn from(stable: StableStore) -> Self {
+        // Releases without roles only knew admins
+        let whitelist = match stable.roles {
+            Some(_roles) => _roles.into_iter().collect(),
+            None => stable
+                .whitelist
+                .into_iter()
+                .map(|principal| (principal, Role::Admin))
+                .collect(),
+        };
+
+        Self {
+            file_id: stable.file_id,
+            files: stable
+                .files
+                .into_iter()
+                .map(|(id, file)| (id, file.into()))
+                .collect(),
+            directory_id: stable.directory_id,
+            directories: stable
+                .directories
+                .into_iter()
+                .map(|(id, directory)| (id, directory.into()))
+                .collect(),
+            chunk_id: stable.chunk_id,
+            chunks: stable.chunks,
+            whitelist,
+            version: stable.version,
+            header_rules: stable.header_rules.unwrap_or_default(),
+            hosts: stable.hosts.unwrap_or_default(),
+            extractions: stable.extractions.unwrap_or_default(),
+            case_insensitive_names: stable.case_insensitive_names.unwrap_or_default(),
+            transfers: stable
+                .transfers
+                .unwrap_or_default()
+                .into_iter()
+                .map(|transfer| (transfer.asset.clone(), transfer))
+                .collect(),
+            // The indexes are not saved, they are rebuilt from the assets after the upgrade
+            ..Default::default()
+        }
+    }
+}
+
+impl From<FileEntity> for StableFileEntity {
+    fn from(file: FileEntity) -> Self {
+        Self {
+            id: file.id,
+            name: file.name,
+            size: file.size,
+            mime_type: file.mime_type,
+            extension: file.extension,
+            permission: file.permission,
+            parent_id: file.parent_id,
+            chunks: file.chunks,
+            metadata: None,
+            is_protected: file.is_protected,
+            owner: file.owner,
+            created_at: file.created_at,
+            updated_at: file.updated_at,
+            version: Some(file.version),
+            variants: Some(file.variants),
+            asset_metadata: Some(file.metadata),
+            headers: Some(file.headers),
+            disposition: Some(file.disposition),
+        }
+    }
+}
+
+impl From<StableFileEntity> for FileEntity {
+    fn from(file: StableFileEntity) -> Self {
+        Self {
+            id: file.id,
+            name: file.name,
+            size: file.size,
+            mime_type: file.mime_type,
+            extension: file.extension,
+            permission: file.permission,
+            parent_id: file.parent_id,
+            chunks: file.chunks,
+            version: file.version.unwrap_or_default(),
+            variants: file.variants.unwrap_or_default(),
+            metadata: file
+                .asset_metadata
+                .unwrap_or_else(|| get_legacy_metadata(file.metadata)),
+            headers: file.headers.unwrap_or_default(),
+            disposition: file.disposition.unwrap_or_default(),
+            is_protected: file.is_protected,
+            owner: file.owner,
+            created_at: file.created_at,
+            updated_at: file.updated_at,
+        }
+    }
+}
+
+impl From<DirectoryEntity> for StableDirectoryEntity {
+    fn from(directory: DirectoryEntity) -> Self {
+        Self {
+            id: directory.id,
+            name: directory.name,
+            parent_id: directory.parent_id,
+            permission: directory.permission,
+            is_protected: directory.is_protected,
+            owner: directory.owner,
+            created_at: directory.created_at,
+            updated_at: directory.updated_at,
+            headers: Some(directory.headers),
+            header_rules: Some(directory.header_rules),
+            asset_metadata: Some(directory.metadata),
+        }
+    }
+}
+
+impl From<StableDirectoryEntity> for DirectoryEntity {
+    fn from(directory: StableDirectoryEntity) -> Self {
+        Self {
+            id: directory.id,
+            name: directory.name,
+            parent_id: directory.parent_id,
+            permission: directory.permission,
+            headers: directory.headers.unwrap_or_default(),
+            header_rules: directory.header_rules.unwrap_or_default(),
+            metadata: directory.asset_metadata.unwrap_or_default(),
+            is_protected: directory.is_protected,
+            owner: directory.owner,
+            created_at: directory.created_at,
+            updated_at: directory.updated_at,
+        }
+    }
+}
+
+// Keep the opaque metadata string of older versions as a text entry
+fn get_legacy_metadata(metadata: Option<String>) -> AssetMetadata {
+    let mut asset_metadata = AssetMetadata::default();
+    if let Some(mut _metadata) = metadata {
+        let mut length = MAX_METADATA_TEXT_LENGTH.min(_metadata.len());
+        while !_metadata.is_char_boundary(length) {
+            length -= 1;
+        }
+        _metadata.truncate(length);
+        asset_metadata.entries.insert(
+            LEGACY_METADATA_KEY.to_string(),
+            MetadataValue::Text(_metadata),
+        );
+    }
+    asset_metadata
+}
@@ -1,11 +1,22 @@
-use std::{cell::RefCell, collections::HashMap};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
 
-use candid::{CandidType, Deserialize, Principal};
-use serde::Serialize;
+use candid::Principal;
 
-use crate::models::{asset_models::Id, directory_models::DirectoryEntity, file_models::FileEntity};
+use crate::models::{
+    archive_models::ArchiveExtraction,
+    asset_models::{AssetWithId, ChildAssets, Id},
+    directory_models::{DirectoryEntity, DirectoryStats},
+    file_models::FileEntity,
+    http_models::HeaderRule,
+    ownership_models::OwnershipTransfer,
+    role_models::Role,
+};
 
-#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+// Saved to stable memory as a `StableStore` on upgrade
+#[derive(Clone, Debug)]
 pub struct Store {
     // Incrementing id for files
     pub file_id: Id,
@@ -18,14 +29,55 @@ pub struct Store {
     // Datastore for directory entities
     pub directories: HashMap<Id, DirectoryEntity>,
 
+    // Index of the children of every directory (None is the root), kept in sync by every mutation
+    pub children: HashMap<Option<Id>, ChildAssets>,
+
+    // Index of the full path of every asset (ex: `directory/file.txt`) used to resolve urls
+    pub paths: HashMap<String, AssetWithId>,
+
+    // Full paths of the files and directories in the path index by their id
+    pub file_paths: HashMap<Id, String>,
+    pub directory_paths: HashMap<Id, String>,
+
+    // Totals of everything inside each directory, kept in sync by every mutation
+    pub directory_stats: HashMap<Id, DirectoryStats>,
+
+    // Index of the assets by their lowercase name used for searching
+    pub names: BTreeMap<String, BTreeSet<AssetWithId>>,
+
+    // Full-text index of the content of text files; term -> file id -> number of occurrences
+    pub text_index: HashMap<String, HashMap<Id, u32>>,
+
+    // Terms in the full-text index by file id, used to remove a file from the index
+    pub text_terms: HashMap<Id, Vec<String>>,
+
     // Incrementing id for chunks
     pub chunk_id: Id,
 
     // Datastore for chunks referenced in files
     pub chunks: HashMap<Id, Vec<u8>>,
 
-    // The principal of the owner
-    pub whitelist: Vec<Principal>,
+    // Index of the file that every chunk belongs to, used to check uploaded chunks
+    pub chunk_files: HashMap<Id, Id>,
+
+    // Header rules loaded from the `_headers` file in the root
+    pub header_rules: Vec<HeaderRule>,
+
+    // Host names mapped to the directory that is used as their document root
+    pub hosts: HashMap<String, Id>,
+
+    // Roles of the principals, the controller that installs the canister becomes the first admin
+    pub whitelist: HashMap<Principal, Role>,
+
+    // Compare the names of sibling assets case insensitive (ex: `Index.html` and `index.html`)
+    pub case_insensitive_names: bool,
+
+    // Running archive extractions by the id of the archive file
+    pub extractions: HashMap<Id, ArchiveExtraction>,
+
+    // Ownership transfers that wait for the new owner to accept them
+    pub transfers: HashMap<AssetWithId, OwnershipTransfer>,
+
     pub version: String,
 }
 
@@ -35,13 +87,29 @@ impl Default for Store {
             file_id: Default::default(),
             files: Default::default(),
 
+            children: Default::default(),
+            paths: Default::default(),
+            file_paths: Default::default(),
+            directory_paths: Default::default(),
+            directory_stats: Default::default(),
+            names: Default::default(),
+            text_index: Default::default(),
+            text_terms: Default::default(),
+
             chunk_id: Default::default(),
             chunks: Default::default(),
+            chunk_files: Default::default(),
 
             directory_id: Default::default(),
             directories: Default::default(),
 
+            header_rules: Default::default(),
+            hosts: Default::default(),
+
             whitelist: Default::default(),
+            case_insensitive_names: false,
+            extractions: Default::default(),
+            transfers: Default::default(),
             version: String::from("0.0.1"),
         }
     }